@@ -5,7 +5,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let target_dir_path = env::var("OUT_DIR").unwrap();
     copy(&target_dir_path, "config.toml");
-    tonic_build::compile_protos("proto/service.proto")?;
+
+    // V1 messages are also sent as JSON to HTTP middlewares
+    let json_messages = ["Header", "QueryParameter", "RequestRequest", "RequestResponse", "ResponseRequest", "ResponseResponse"];
+    let builder = json_messages.iter().fold(tonic_build::configure(), |builder, name| {
+        builder.type_attribute(format!(".kubeware.{}", name), "#[derive(serde_derive::Serialize, serde_derive::Deserialize)] #[serde(rename_all = \"camelCase\", default)]")
+    });
+
+    builder
+        .field_attribute(".kubeware.RequestResponse.status", "#[serde(with = \"crate::webhook::status\")]")
+        .field_attribute(".kubeware.ResponseResponse.status", "#[serde(with = \"crate::webhook::status\")]")
+        .compile(&["proto/service.proto", "proto/envoy/ext_authz.proto", "proto/envoy/ext_proc.proto"], &["proto"])?;
+
    Ok(())
 }
 
@@ -0,0 +1,6 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tonic_build::compile_protos("proto/service.proto")?;
+    tonic_build::compile_protos("proto/health.proto")?;
+
+    Ok(())
+}
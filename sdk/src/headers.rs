@@ -0,0 +1,19 @@
+use crate::proto::Header;
+
+// Header names are case-insensitive, kubeware forwards them lowercased
+pub fn get<'a>(headers: &'a [Header], name: &str) -> Option<&'a str> {
+    headers.iter().find(|x| x.name.eq_ignore_ascii_case(name)).map(|x| x.value.as_str())
+}
+
+// Repeated headers (e.g. set-cookie) are separate entries
+pub fn get_all<'a>(headers: &'a [Header], name: &str) -> Vec<&'a str> {
+    headers.iter().filter(|x| x.name.eq_ignore_ascii_case(name)).map(|x| x.value.as_str()).collect()
+}
+
+pub fn contains(headers: &[Header], name: &str) -> bool {
+    headers.iter().any(|x| x.name.eq_ignore_ascii_case(name))
+}
+
+pub fn header(name: &str, value: &str) -> Header {
+    Header { name: name.to_lowercase(), value: value.to_string() }
+}
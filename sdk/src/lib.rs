@@ -0,0 +1,28 @@
+// Building blocks for kubeware middlewares written in Rust, see the SDK section of the README
+mod request;
+mod response;
+mod service;
+mod server;
+pub mod headers;
+
+#[macro_use]
+extern crate log;
+
+pub use crate::request::{Request, RequestReply};
+pub use crate::response::{Response, ResponseReply};
+pub use crate::service::{Handler, Description, MiddlewareService, Result, PROTOCOL_VERSION};
+pub use crate::server::{Server, HealthService, serve, sigterm_signal};
+pub use tonic::async_trait;
+
+// Generated kubeware messages, for whatever the wrappers don't cover.
+// sdk/proto/service.proto is a copy of proto/service.proto so the crate packages on its own, the sdk tests keep both equal.
+// Oneofs mixing messages and body chunks are generated as enums of very different sizes.
+#[allow(clippy::large_enum_variant)]
+pub mod proto {
+    tonic::include_proto!("kubeware");
+}
+
+// gRPC health checking, see proto/health.proto
+pub mod health {
+    tonic::include_proto!("grpc.health.v1");
+}
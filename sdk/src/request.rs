@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use crate::headers;
+use crate::proto::{Header, QueryParameter, RequestRequest, RequestResponse, ResponseStatus};
+
+// Request as seen by the request stage, bodies are only set when the middleware asked for them
+pub struct Request {
+    inner: RequestRequest
+}
+
+impl Request {
+    pub fn method(&self) -> &str {
+        &self.inner.method
+    }
+
+    pub fn uri(&self) -> &str {
+        &self.inner.uri
+    }
+
+    pub fn scheme(&self) -> &str {
+        &self.inner.scheme
+    }
+
+    pub fn host(&self) -> &str {
+        &self.inner.host
+    }
+
+    pub fn path(&self) -> &str {
+        &self.inner.path
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        headers::get(&self.inner.headers, name)
+    }
+
+    pub fn headers(&self) -> &[Header] {
+        &self.inner.headers
+    }
+
+    // First value of a repeated parameter
+    pub fn query(&self, name: &str) -> Option<&str> {
+        self.inner.query_params.iter().find(|x| x.name == name).map(|x| x.value.as_str())
+    }
+
+    pub fn query_params(&self) -> &[QueryParameter] {
+        &self.inner.query_params
+    }
+
+    pub fn body(&self) -> &str {
+        &self.inner.body
+    }
+
+    pub fn body_truncated(&self) -> bool {
+        self.inner.body_truncated
+    }
+
+    pub fn context(&self, key: &str) -> Option<&str> {
+        self.inner.context.get(key).map(|x| x.as_str())
+    }
+
+    pub fn into_inner(self) -> RequestRequest {
+        self.inner
+    }
+}
+
+impl From<RequestRequest> for Request {
+    fn from(inner: RequestRequest) -> Request {
+        Request { inner }
+    }
+}
+
+// Answer to the request stage, e.g. RequestReply::stop(401).body("Unauthorized")
+pub struct RequestReply {
+    inner: RequestResponse
+}
+
+impl RequestReply {
+    fn with_status(status: ResponseStatus) -> RequestReply {
+        RequestReply { inner: RequestResponse { status: status as i32, context: HashMap::new(), ..Default::default() } }
+    }
+
+    // Changes are applied and the request goes on
+    pub fn success() -> RequestReply {
+        RequestReply::with_status(ResponseStatus::Success)
+    }
+
+    // Request goes on untouched, changes are ignored
+    pub fn proceed() -> RequestReply {
+        RequestReply::with_status(ResponseStatus::Continue)
+    }
+
+    // Pipeline ends, the backend isn't called
+    pub fn stop(status_code: u32) -> RequestReply {
+        RequestReply::with_status(ResponseStatus::Stop).status_code(status_code)
+    }
+
+    // Backend isn't called, the response still goes through the response stage
+    pub fn respond(status_code: u32) -> RequestReply {
+        RequestReply::with_status(ResponseStatus::Respond).status_code(status_code)
+    }
+
+    // Changes are applied and the rest of the request stage is skipped
+    pub fn skip_stage() -> RequestReply {
+        RequestReply::with_status(ResponseStatus::SkipStage)
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> RequestReply {
+        self.inner.added_headers.push(headers::header(name, value));
+        self
+    }
+
+    pub fn remove_header(mut self, name: &str) -> RequestReply {
+        self.inner.removed_headers.push(name.to_lowercase());
+        self
+    }
+
+    pub fn body<T: Into<String>>(mut self, body: T) -> RequestReply {
+        self.inner.body = Some(body.into());
+        self
+    }
+
+    pub fn status_code(mut self, status_code: u32) -> RequestReply {
+        self.inner.status_code = Some(status_code);
+        self
+    }
+
+    pub fn context(mut self, key: &str, value: &str) -> RequestReply {
+        self.inner.context.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn method(mut self, method: &str) -> RequestReply {
+        self.inner.method = Some(method.to_string());
+        self
+    }
+
+    pub fn path(mut self, path: &str) -> RequestReply {
+        self.inner.path = Some(path.to_string());
+        self
+    }
+
+    // Replaces the whole query string
+    pub fn query(mut self, query: &str) -> RequestReply {
+        self.inner.query = Some(query.to_string());
+        self
+    }
+
+    pub fn query_param(mut self, name: &str, value: &str) -> RequestReply {
+        self.inner.added_query_params.push(QueryParameter { name: name.to_string(), value: value.to_string() });
+        self
+    }
+
+    pub fn remove_query_param(mut self, name: &str) -> RequestReply {
+        self.inner.removed_query_params.push(name.to_string());
+        self
+    }
+
+    // One of the named backends
+    pub fn backend(mut self, backend: &str) -> RequestReply {
+        self.inner.backend = Some(backend.to_string());
+        self
+    }
+}
+
+impl From<RequestReply> for RequestResponse {
+    fn from(reply: RequestReply) -> RequestResponse {
+        reply.inner
+    }
+}
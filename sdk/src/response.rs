@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use crate::headers;
+use crate::proto::{Header, ResponseRequest, ResponseResponse, ResponseStatus};
+
+// Backend response with the request it answers, bodies are only set when the middleware asked for them
+pub struct Response {
+    inner: ResponseRequest
+}
+
+impl Response {
+    pub fn method(&self) -> &str {
+        &self.inner.method
+    }
+
+    pub fn uri(&self) -> &str {
+        &self.inner.uri
+    }
+
+    pub fn request_header(&self, name: &str) -> Option<&str> {
+        headers::get(&self.inner.request_headers, name)
+    }
+
+    pub fn request_headers(&self) -> &[Header] {
+        &self.inner.request_headers
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        headers::get(&self.inner.response_headers, name)
+    }
+
+    pub fn headers(&self) -> &[Header] {
+        &self.inner.response_headers
+    }
+
+    pub fn request_body(&self) -> &str {
+        &self.inner.request_body
+    }
+
+    pub fn body(&self) -> &str {
+        &self.inner.response_body
+    }
+
+    pub fn body_truncated(&self) -> bool {
+        self.inner.response_body_truncated
+    }
+
+    pub fn context(&self, key: &str) -> Option<&str> {
+        self.inner.context.get(key).map(|x| x.as_str())
+    }
+
+    pub fn into_inner(self) -> ResponseRequest {
+        self.inner
+    }
+}
+
+impl From<ResponseRequest> for Response {
+    fn from(inner: ResponseRequest) -> Response {
+        Response { inner }
+    }
+}
+
+// Answer to the response stage, e.g. ResponseReply::success().header("cache-control", "no-store")
+pub struct ResponseReply {
+    inner: ResponseResponse
+}
+
+impl ResponseReply {
+    fn with_status(status: ResponseStatus) -> ResponseReply {
+        ResponseReply { inner: ResponseResponse { status: status as i32, context: HashMap::new(), ..Default::default() } }
+    }
+
+    // Changes are applied and the response goes on
+    pub fn success() -> ResponseReply {
+        ResponseReply::with_status(ResponseStatus::Success)
+    }
+
+    // Response goes on untouched, changes are ignored
+    pub fn proceed() -> ResponseReply {
+        ResponseReply::with_status(ResponseStatus::Continue)
+    }
+
+    // Changes are applied and the response is returned right away with this status, remaining middlewares don't run
+    pub fn stop(status_code: u32) -> ResponseReply {
+        ResponseReply::with_status(ResponseStatus::Stop).status_code(status_code)
+    }
+
+    // Changes are applied and the rest of the response stage is skipped
+    pub fn skip_stage() -> ResponseReply {
+        ResponseReply::with_status(ResponseStatus::SkipStage)
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> ResponseReply {
+        self.inner.added_headers.push(headers::header(name, value));
+        self
+    }
+
+    pub fn remove_header(mut self, name: &str) -> ResponseReply {
+        self.inner.removed_headers.push(name.to_lowercase());
+        self
+    }
+
+    pub fn body<T: Into<String>>(mut self, body: T) -> ResponseReply {
+        self.inner.body = Some(body.into());
+        self
+    }
+
+    pub fn status_code(mut self, status_code: u32) -> ResponseReply {
+        self.inner.status_code = Some(status_code);
+        self
+    }
+
+    pub fn context(mut self, key: &str, value: &str) -> ResponseReply {
+        self.inner.context.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+impl From<ResponseReply> for ResponseResponse {
+    fn from(reply: ResponseReply) -> ResponseResponse {
+        reply.inner
+    }
+}
@@ -0,0 +1,125 @@
+use std::future::Future;
+use std::net::ToSocketAddrs;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use futures::future;
+use tokio::signal::unix::{signal, SignalKind};
+use tonic::{Request as TonicRequest, Response as TonicResponse, Status};
+use tonic::transport::Server as TonicServer;
+use crate::health::{HealthCheckRequest, HealthCheckResponse};
+use crate::health::health_check_response::ServingStatus;
+use crate::health::health_server::{Health, HealthServer};
+use crate::proto::middleware_server::MiddlewareServer;
+use crate::service::{Handler, Description, MiddlewareService, Result};
+
+type Signal = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+// Services a check can name, the empty one is the server as a whole
+const HEALTH_SERVICES: [&str; 2] = ["", "kubeware.Middleware"];
+
+// grpc.health.v1 for probes (e.g. grpc_health_probe), NOT_SERVING once shutting down
+pub struct HealthService {
+    serving: Arc<AtomicBool>
+}
+
+#[tonic::async_trait]
+impl Health for HealthService {
+    async fn check(&self, request: TonicRequest<HealthCheckRequest>) -> std::result::Result<TonicResponse<HealthCheckResponse>, Status> {
+        let service = request.into_inner().service;
+
+        if !HEALTH_SERVICES.contains(&service.as_str()) {
+            return Err(Status::not_found(format!("Unknown service {}", service)))
+        }
+
+        let status = match self.serving.load(Ordering::SeqCst) {
+            true => ServingStatus::Serving,
+            false => ServingStatus::NotServing
+        };
+
+        Ok(TonicResponse::new(HealthCheckResponse { status: status as i32 }))
+    }
+}
+
+// Middleware and health services on one port, e.g. Server::new(handler).description(...).serve("0.0.0.0:50051")
+pub struct Server<H> {
+    handler: H,
+    description: Option<Description>,
+    shutdown: Option<Signal>,
+    pre_stop_delay: Duration
+}
+
+impl<H: Handler> Server<H> {
+    pub fn new(handler: H) -> Server<H> {
+        Server {
+            handler,
+            description: None,
+            shutdown: None,
+            pre_stop_delay: Duration::from_millis(0)
+        }
+    }
+
+    // Without it Describe answers UNIMPLEMENTED and kubeware's config is used as is
+    pub fn description(mut self, description: Description) -> Server<H> {
+        self.description = Some(description);
+        self
+    }
+
+    // Defaults to SIGTERM or SIGINT
+    pub fn shutdown<F: Future<Output = ()> + Send + 'static>(mut self, signal: F) -> Server<H> {
+        self.shutdown = Some(Box::pin(signal));
+        self
+    }
+
+    // Health turns NOT_SERVING right away, calls are still accepted for this long before draining
+    pub fn pre_stop_delay(mut self, pre_stop_delay: Duration) -> Server<H> {
+        self.pre_stop_delay = pre_stop_delay;
+        self
+    }
+
+    // Returns once the shutdown signal fired and in-flight calls finished
+    pub async fn serve(self, address: &str) -> Result<()> {
+        let address = address.to_socket_addrs()?.next().ok_or("Address didn't resolve")?;
+        let serving = Arc::new(AtomicBool::new(true));
+        let trigger = self.shutdown.unwrap_or_else(|| Box::pin(sigterm_signal()));
+        let pre_stop_delay = self.pre_stop_delay;
+        let health = HealthService { serving: Arc::clone(&serving) };
+
+        let stop_accepting = async move {
+            trigger.await;
+
+            info!("Shutting down, waiting {} ms before draining calls.", pre_stop_delay.as_millis());
+            serving.store(false, Ordering::SeqCst);
+
+            if pre_stop_delay > Duration::from_millis(0) {
+                tokio::time::delay_for(pre_stop_delay).await;
+            }
+        };
+
+        info!("Middleware listening on {}.", address);
+
+        TonicServer::builder()
+            .add_service(MiddlewareServer::new(MiddlewareService::new(self.handler, self.description)))
+            .add_service(HealthServer::new(health))
+            .serve_with_shutdown(address, stop_accepting)
+            .await?;
+
+        Ok(())
+    }
+}
+
+// One-liner for handlers which don't need a description nor a custom shutdown
+pub async fn serve<H: Handler>(handler: H, address: &str) -> Result<()> {
+    Server::new(handler).serve(address).await
+}
+
+// Also what the kubeware binary waits for before shutting down
+pub async fn sigterm_signal() {
+    let mut terminate = signal(SignalKind::terminate())
+        .expect("failed to install SIGTERM handler");
+    let mut interrupt = signal(SignalKind::interrupt())
+        .expect("failed to install SIGINT handler");
+
+    future::select(Box::pin(terminate.recv()), Box::pin(interrupt.recv())).await;
+}
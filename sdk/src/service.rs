@@ -0,0 +1,174 @@
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tonic::{Request as TonicRequest, Response as TonicResponse, Status, Streaming};
+use crate::proto::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse, RequestResponseV2, ResponseResponseV2};
+use crate::proto::{RequestStreamMessage, ResponseStreamMessage, SessionEvent, SessionDecision, ErrorRequest, DescribeRequest, DescribeResponse};
+use crate::proto::{request_stream_message, response_stream_message};
+use crate::proto::middleware_server::Middleware;
+use crate::request::{Request, RequestReply};
+use crate::response::{Response, ResponseReply};
+
+type GenericError = Box<dyn std::error::Error + Send + Sync>;
+pub type Result<T> = std::result::Result<T, GenericError>;
+
+// Version of the kubeware protocol, kubeware itself reuses it
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// What a middleware implements, stages which aren't implemented let everything through.
+// Errors are answered with INTERNAL, kubeware counts the middleware as unavailable then.
+#[tonic::async_trait]
+pub trait Handler: Send + Sync + 'static {
+    async fn request(&self, _request: Request) -> Result<RequestReply> {
+        Ok(RequestReply::proceed())
+    }
+
+    async fn response(&self, _response: Response) -> Result<ResponseReply> {
+        Ok(ResponseReply::proceed())
+    }
+}
+
+// Answer to Describe, values set in kubeware's config still win
+#[derive(Clone)]
+pub struct Description {
+    inner: DescribeResponse
+}
+
+impl Description {
+    pub fn new(name: &str) -> Description {
+        Description { inner: DescribeResponse { name: name.to_string(), protocol_version: PROTOCOL_VERSION, ..Default::default() } }
+    }
+
+    pub fn request(mut self, request: bool) -> Description {
+        self.inner.request = request;
+        self
+    }
+
+    pub fn response(mut self, response: bool) -> Description {
+        self.inner.response = response;
+        self
+    }
+
+    pub fn request_body(mut self, request_body: bool) -> Description {
+        self.inner.request_body = request_body;
+        self
+    }
+
+    pub fn response_body(mut self, response_body: bool) -> Description {
+        self.inner.response_body = response_body;
+        self
+    }
+
+    // Only these headers are sent, all of them when empty
+    pub fn headers(mut self, headers: &[&str]) -> Description {
+        self.inner.headers = headers.iter().map(|x| x.to_lowercase()).collect();
+        self
+    }
+
+    pub fn timeout_ms(mut self, timeout_ms: u32) -> Description {
+        self.inner.timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    // Bodies are streamed in chunks and put back together before the handler is called
+    pub fn stream_body(mut self, stream_body: bool) -> Description {
+        self.inner.stream_body = stream_body;
+        self
+    }
+}
+
+// Generated Middleware service on top of a Handler, only the v1 messages are supported
+pub struct MiddlewareService<H> {
+    handler: Arc<H>,
+    description: Option<Description>
+}
+
+impl<H: Handler> MiddlewareService<H> {
+    pub fn new(handler: H, description: Option<Description>) -> MiddlewareService<H> {
+        MiddlewareService { handler: Arc::new(handler), description }
+    }
+}
+
+fn internal(err: GenericError) -> Status {
+    error!("Handler failed. {}", err);
+
+    Status::internal(err.to_string())
+}
+
+#[tonic::async_trait]
+impl<H: Handler> Middleware for MiddlewareService<H> {
+    async fn handle_request(&self, request: TonicRequest<RequestRequest>) -> std::result::Result<TonicResponse<RequestResponse>, Status> {
+        match self.handler.request(Request::from(request.into_inner())).await {
+            Ok(val) => Ok(TonicResponse::new(val.into())),
+            Err(err) => Err(internal(err))
+        }
+    }
+
+    async fn handle_response(&self, request: TonicRequest<ResponseRequest>) -> std::result::Result<TonicResponse<ResponseResponse>, Status> {
+        match self.handler.response(Response::from(request.into_inner())).await {
+            Ok(val) => Ok(TonicResponse::new(val.into())),
+            Err(err) => Err(internal(err))
+        }
+    }
+
+    async fn handle_request_v2(&self, _request: TonicRequest<RequestRequest>) -> std::result::Result<TonicResponse<RequestResponseV2>, Status> {
+        Err(Status::unimplemented("HandleRequestV2 is not implemented"))
+    }
+
+    async fn handle_response_v2(&self, _request: TonicRequest<ResponseRequest>) -> std::result::Result<TonicResponse<ResponseResponseV2>, Status> {
+        Err(Status::unimplemented("HandleResponseV2 is not implemented"))
+    }
+
+    // Streamed messages are put back together, so handlers don't need to know about chunks
+    async fn handle_request_stream(&self, request: TonicRequest<Streaming<RequestStreamMessage>>) -> std::result::Result<TonicResponse<RequestResponse>, Status> {
+        let mut inbound = request.into_inner();
+        let mut message = RequestRequest::default();
+        let mut body = Vec::new();
+
+        while let Some(item) = inbound.message().await? {
+            match item.message {
+                Some(request_stream_message::Message::Request(val)) => message = val,
+                Some(request_stream_message::Message::Chunk(val)) => body.extend(val.data),
+                None => ()
+            }
+        }
+
+        message.body = String::from_utf8_lossy(&body).to_string();
+
+        self.handle_request(TonicRequest::new(message)).await
+    }
+
+    async fn handle_response_stream(&self, request: TonicRequest<Streaming<ResponseStreamMessage>>) -> std::result::Result<TonicResponse<ResponseResponse>, Status> {
+        let mut inbound = request.into_inner();
+        let mut message = ResponseRequest::default();
+        let mut body = Vec::new();
+
+        while let Some(item) = inbound.message().await? {
+            match item.message {
+                Some(response_stream_message::Message::Response(val)) => message = val,
+                Some(response_stream_message::Message::Chunk(val)) => body.extend(val.data),
+                None => ()
+            }
+        }
+
+        message.response_body = String::from_utf8_lossy(&body).to_string();
+
+        self.handle_response(TonicRequest::new(message)).await
+    }
+
+    type SessionStream = mpsc::Receiver<std::result::Result<SessionDecision, Status>>;
+
+    async fn session(&self, _request: TonicRequest<Streaming<SessionEvent>>) -> std::result::Result<TonicResponse<Self::SessionStream>, Status> {
+        Err(Status::unimplemented("Session is not implemented"))
+    }
+
+    async fn handle_error(&self, _request: TonicRequest<ErrorRequest>) -> std::result::Result<TonicResponse<ResponseResponse>, Status> {
+        Err(Status::unimplemented("HandleError is not implemented"))
+    }
+
+    async fn describe(&self, _request: TonicRequest<DescribeRequest>) -> std::result::Result<TonicResponse<DescribeResponse>, Status> {
+        match &self.description {
+            Some(val) => Ok(TonicResponse::new(val.inner.clone())),
+            None => Err(Status::unimplemented("Describe is not implemented"))
+        }
+    }
+}
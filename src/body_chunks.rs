@@ -0,0 +1,36 @@
+use bytes::Bytes;
+use futures::stream::{self, Stream};
+use crate::kubeware::{BodyChunk, RequestRequest, ResponseRequest, RequestStreamMessage, ResponseStreamMessage};
+use crate::kubeware::{request_stream_message, response_stream_message};
+
+const CHUNK_BYTES: usize = 64 * 1024;
+
+// Chunks are copied out of the buffered body one at a time, as the stream is polled.
+// The last one is always sent, even for an empty body.
+pub fn chunks(body: Bytes) -> impl Iterator<Item = BodyChunk> + Send + Sync {
+    let count = std::cmp::max(1, body.len().div_ceil(CHUNK_BYTES));
+
+    (0..count).map(move |index| {
+        let start = index * CHUNK_BYTES;
+        let end = std::cmp::min(body.len(), start + CHUNK_BYTES);
+
+        BodyChunk {
+            data: body.slice(start..end).to_vec(),
+            last: index + 1 == count
+        }
+    })
+}
+
+pub fn request(message: RequestRequest, body: Bytes) -> impl Stream<Item = RequestStreamMessage> + Send + Sync {
+    let head = std::iter::once(request_stream_message::Message::Request(message));
+    let messages = head.chain(chunks(body).map(request_stream_message::Message::Chunk));
+
+    stream::iter(messages.map(|x| RequestStreamMessage { message: Some(x) }))
+}
+
+pub fn response(message: ResponseRequest, body: Bytes) -> impl Stream<Item = ResponseStreamMessage> + Send + Sync {
+    let head = std::iter::once(response_stream_message::Message::Response(message));
+    let messages = head.chain(chunks(body).map(response_stream_message::Message::Chunk));
+
+    stream::iter(messages.map(|x| ResponseStreamMessage { message: Some(x) }))
+}
@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use toml::value::Table;
+use crate::config::MiddlewareConfig;
+use crate::handler::MiddlewareHandler;
+use crate::middleware::Middleware;
+use crate::container_handler::{ContainerHandler, RequestAnswer, ResponseAnswer};
+use crate::kubeware::{RequestResponseV2, ResponseResponseV2, ResponseStatus, Mutation, Header};
+use crate::kubeware::mutation::Mutation::*;
+
+type GenericError = Box<dyn std::error::Error + Send + Sync>;
+type Result<T> = std::result::Result<T, GenericError>;
+
+pub const HEADERS: &str = "headers";
+pub const REQUEST_ID: &str = "request_id";
+
+const DEFAULT_REQUEST_ID_HEADER: &str = "x-request-id";
+
+static REQUEST_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// Sets and removes fixed headers, e.g. options = { request = { "x-env" = "prod" }, remove_response = ["server"] }
+pub struct Headers {
+    request: Vec<Mutation>,
+    response: Vec<Mutation>
+}
+
+impl Headers {
+    pub fn from_config(config: &MiddlewareConfig) -> Result<Arc<dyn MiddlewareHandler>> {
+        let options = config.options.clone().unwrap_or_default();
+
+        Ok(Arc::new(Headers {
+            request: Headers::mutations(&options, "request", "remove_request")?,
+            response: Headers::mutations(&options, "response", "remove_response")?
+        }))
+    }
+
+    fn mutations(options: &Table, set: &str, remove: &str) -> Result<Vec<Mutation>> {
+        let mut mutations = Vec::new();
+
+        for (name, value) in table(options, set)? {
+            let value = value.as_str().ok_or_else(|| format!("Header {} in options.{} is not a string", name, set))?;
+            mutations.push(SetHeader(Header { name: name.to_lowercase(), value: value.to_string() }));
+        }
+
+        for name in strings(options, remove)? {
+            mutations.push(RemoveHeader(name));
+        }
+
+        Ok(mutations.into_iter().map(|x| Mutation { mutation: Some(x) }).collect())
+    }
+}
+
+#[async_trait]
+impl MiddlewareHandler for Headers {
+    async fn request(&self, _container: &mut ContainerHandler, _middleware: &Middleware) -> Result<RequestAnswer> {
+        Ok(RequestAnswer::V2(RequestResponseV2 { status: ResponseStatus::Success as i32, mutations: self.request.clone(), ..Default::default() }))
+    }
+
+    async fn response(&self, _container: &mut ContainerHandler, _middleware: &Middleware) -> Result<ResponseAnswer> {
+        Ok(ResponseAnswer::V2(ResponseResponseV2 { status: ResponseStatus::Success as i32, mutations: self.response.clone(), ..Default::default() }))
+    }
+}
+
+// Gives requests without one an id, and returns it with the response, options = { header = "x-correlation-id" }
+pub struct RequestId {
+    header: String
+}
+
+impl RequestId {
+    pub fn from_config(config: &MiddlewareConfig) -> Result<Arc<dyn MiddlewareHandler>> {
+        let header = match config.options.as_ref().and_then(|x| x.get("header")) {
+            Some(val) => val.as_str().ok_or("options.header is not a string")?.to_lowercase(),
+            None => DEFAULT_REQUEST_ID_HEADER.to_string()
+        };
+
+        Ok(Arc::new(RequestId { header }))
+    }
+
+    // Unique within the process, time first so ids sort by arrival
+    fn generate() -> String {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_nanos() as u64).unwrap_or_default();
+        let counter = REQUEST_ID_COUNTER.fetch_add(1, Ordering::Relaxed) as u32;
+
+        format!("{:016x}{:08x}", nanos, counter)
+    }
+
+    fn find(&self, headers: &[Header]) -> Option<String> {
+        headers.iter().find(|x| x.name.to_lowercase() == self.header).map(|x| x.value.clone())
+    }
+}
+
+#[async_trait]
+impl MiddlewareHandler for RequestId {
+    async fn request(&self, container: &mut ContainerHandler, middleware: &Middleware) -> Result<RequestAnswer> {
+        let mutations = match self.find(&container.into_middleware_request(middleware)?.headers) {
+            Some(_) => Vec::new(),
+            None => vec![Mutation { mutation: Some(SetHeader(Header { name: self.header.clone(), value: RequestId::generate() })) }]
+        };
+
+        Ok(RequestAnswer::V2(RequestResponseV2 { status: ResponseStatus::Success as i32, mutations, ..Default::default() }))
+    }
+
+    async fn response(&self, container: &mut ContainerHandler, middleware: &Middleware) -> Result<ResponseAnswer> {
+        let mutations = self.find(&container.into_middleware_response(middleware)?.request_headers)
+            .map(|value| Mutation { mutation: Some(SetHeader(Header { name: self.header.clone(), value })) })
+            .into_iter()
+            .collect();
+
+        Ok(ResponseAnswer::V2(ResponseResponseV2 { status: ResponseStatus::Success as i32, mutations, ..Default::default() }))
+    }
+}
+
+fn table(options: &Table, key: &str) -> Result<Table> {
+    match options.get(key) {
+        Some(val) => Ok(val.as_table().ok_or_else(|| format!("options.{} is not a table", key))?.clone()),
+        None => Ok(Table::new())
+    }
+}
+
+fn strings(options: &Table, key: &str) -> Result<Vec<String>> {
+    match options.get(key) {
+        Some(val) => val.as_array().ok_or_else(|| format!("options.{} is not a list", key))?
+            .iter()
+            .map(|x| x.as_str().map(|x| x.to_lowercase()).ok_or_else(|| format!("options.{} holds a value which is not a string", key).into()))
+            .collect(),
+        None => Ok(Vec::new())
+    }
+}
@@ -1,24 +1,129 @@
 use serde_derive::Deserialize;
+use std::collections::HashMap;
 
 #[derive(Deserialize,Debug,Clone)]
 pub struct Config {
     pub ip: Option<String>,
     pub port: Option<u16>,
     pub log: Option<String>,
-    pub backend: Backend,
-    #[serde(rename = "middleware")]
-    pub middlewares: Vec<MiddlewareConfig>
+    pub max_request_body_bytes: Option<u64>,
+    pub max_response_body_bytes: Option<u64>,
+    pub max_middleware_body_bytes: Option<u64>,
+    pub readiness_path: Option<String>,
+    pub shutdown: Option<Shutdown>,
+    pub listener: Option<Listener>,
+    pub pipeline: Option<Pipeline>,
+    // Required by the binary, the tower layer defaults it as the wrapped service is the backend
+    pub backend: Option<Backend>,
+    #[serde(default)]
+    pub backends: HashMap<String, Backend>,
+    #[serde(rename = "middleware", default)]
+    pub middlewares: Vec<MiddlewareConfig>,
+    #[serde(rename = "route", default)]
+    pub routes: Vec<Route>
+}
+
+impl Config {
+    // Default backend, or one of the named backends a middleware routed the request to
+    pub fn backend_by_name(&self, name: Option<&str>) -> Option<&Backend> {
+        match name {
+            Some(val) => self.backends.get(val),
+            None => self.backend.as_ref()
+        }
+    }
 }
 
 #[derive(Deserialize,Debug,Clone)]
 pub struct MiddlewareConfig {
+    #[serde(default)]
     pub url: String,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub options: Option<toml::value::Table>,
+    pub protocol: Option<Protocol>,
     pub timeout_ms: Option<u32>,
-    pub request: bool,
-    pub response: bool
+    pub request: Option<bool>,
+    pub response: Option<bool>,
+    pub request_body: Option<bool>,
+    pub response_body: Option<bool>,
+    pub headers: Option<Vec<String>>,
+    pub error: Option<bool>,
+    pub api_version: Option<u32>,
+    pub session: Option<bool>,
+    pub stream_body: Option<bool>,
+    pub max_fuel: Option<u64>,
+    pub max_memory_bytes: Option<u64>,
+    pub max_string_size: Option<usize>,
+    pub max_array_size: Option<usize>,
+    pub max_map_size: Option<usize>,
+    pub order: Option<i32>,
+    pub phase: Option<Phase>
 }
 
 #[derive(Deserialize,Debug,Clone)]
+pub struct Pipeline {
+    pub response_order: Option<ResponseOrder>,
+    pub request_stop: Option<RequestStop>
+}
+
+#[derive(Deserialize,Debug,Clone,Copy,PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    Grpc,
+    Http,
+    ExtAuthz,
+    ExtProc,
+    Wasm,
+    Script
+}
+
+#[derive(Deserialize,Debug,Clone,PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseOrder {
+    Declaration,
+    Reverse
+}
+
+#[derive(Deserialize,Debug,Clone,PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestStop {
+    End,
+    ResponseStage
+}
+
+// Variants are declared in execution order
+#[derive(Deserialize,Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Early,
+    Normal,
+    Late
+}
+
+#[derive(Deserialize,Debug,Clone)]
+pub struct Route {
+    pub path: String,
+    pub max_request_body_bytes: Option<u64>,
+    pub max_response_body_bytes: Option<u64>
+}
+
+#[derive(Deserialize,Debug,Clone)]
+pub struct Shutdown {
+    pub pre_stop_delay_ms: Option<u32>,
+    pub drain_timeout_ms: Option<u32>
+}
+
+#[derive(Deserialize,Debug,Clone)]
+pub struct Listener {
+    pub version: Option<HttpVersion>,
+    pub http2_max_concurrent_streams: Option<u32>,
+    pub http2_initial_stream_window_size: Option<u32>,
+    pub http2_initial_connection_window_size: Option<u32>,
+    pub http2_keep_alive_interval_ms: Option<u32>,
+    pub http2_keep_alive_timeout_ms: Option<u32>
+}
+
+#[derive(Deserialize,Debug,Clone,Default)]
 pub struct Backend {
     pub url: String,
     pub timeout_ms: Option<u32>,
@@ -1,18 +1,66 @@
 use crate::request_container::{RequestContainer, RequestContainerBuilder, ContainerState};
-use hyper::{Request, Body, Response};
-use hyper::header::{HeaderName, HeaderValue, CONTENT_LENGTH};
-use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse};
+use hyper::{Request, Body, Response, HeaderMap};
+use hyper::header::{HeaderName, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse, QueryParameter, Header, ErrorRequest, ErrorKind, ResponseStatus};
+use crate::kubeware::{RequestResponseV2, ResponseResponseV2, Mutation};
+use crate::kubeware::mutation::Mutation::*;
+use crate::middleware::Middleware;
 use std::time::{Duration, Instant};
 use hyper::http::method::Method;
 use std::str::FromStr;
+use std::collections::HashMap;
 use crate::{KUBEWARE_TIME_HEADER, BACKEND_TIME_HEADER};
+use crate::grpc;
+use crate::limits;
+use crate::query;
+use hyper::{Uri, Version};
+use crate::trailers::Trailers;
+use crate::session::Session;
+use crate::ext_proc;
+use bytes::Bytes;
 
 type GenericError = Box<dyn std::error::Error + Send + Sync>;
 type Result<T> = std::result::Result<T, GenericError>;
 
+// Middleware answer, in the format matching its api version
+pub enum RequestAnswer {
+    V1(RequestResponse),
+    V2(RequestResponseV2)
+}
+
+pub enum ResponseAnswer {
+    V1(ResponseResponse),
+    V2(ResponseResponseV2)
+}
+
+impl RequestAnswer {
+    pub fn status(&self) -> Option<ResponseStatus> {
+        match self {
+            RequestAnswer::V1(val) => ResponseStatus::from_i32(val.status),
+            RequestAnswer::V2(val) => ResponseStatus::from_i32(val.status)
+        }
+    }
+}
+
+impl ResponseAnswer {
+    pub fn status(&self) -> Option<ResponseStatus> {
+        match self {
+            ResponseAnswer::V1(val) => ResponseStatus::from_i32(val.status),
+            ResponseAnswer::V2(val) => ResponseStatus::from_i32(val.status)
+        }
+    }
+}
+
 pub struct ContainerHandler {
     container: RequestContainer,
-    url: String,
+    backend: Option<String>,
+    request_stream: Option<Body>,
+    response_stream: Option<Body>,
+    middleware_body_limit: Option<u64>,
+    context: HashMap<String, String>,
+    trailers: HeaderMap,
+    sessions: HashMap<usize, Session>,
+    processors: HashMap<usize, ext_proc::Stream>,
     backend_elapsed: Option<Duration>,
     timer: Instant
 }
@@ -26,7 +74,55 @@ impl ContainerHandler {
 
     pub fn timer(&mut self) -> Instant { self.timer }
 
-    pub async fn new(request: Request<Body>, url: String) -> Result<ContainerHandler> {
+    pub fn status_code_default(&mut self, status_code: u16) {
+        if self.container.status_code().is_none() {
+            self.container.status_code_set(status_code)
+        }
+    }
+
+    pub fn middleware_body_limit_set(&mut self, limit: Option<u64>) { self.middleware_body_limit = limit }
+
+    pub fn backend(&self) -> Option<&str> { self.backend.as_deref() }
+
+    // Sessions are taken out while in use, a failed call drops them
+    pub fn session_take(&mut self, id: usize) -> Option<Session> { self.sessions.remove(&id) }
+
+    pub fn session_set(&mut self, id: usize, session: Session) { self.sessions.insert(id, session); }
+
+    // ext_proc streams are kept the same way as sessions
+    pub fn processor_take(&mut self, id: usize) -> Option<ext_proc::Stream> { self.processors.remove(&id) }
+
+    pub fn processor_set(&mut self, id: usize, stream: ext_proc::Stream) { self.processors.insert(id, stream); }
+
+    pub fn status_code(&self) -> Option<u16> { self.container.status_code() }
+
+    // Real bodies, the ones sent to middlewares can be masked or left out while streaming
+    pub fn request_has_body(&self) -> bool {
+        ContainerHandler::has_body(&self.container.request_headers(), self.container.request_body_bytes(), self.request_stream.as_ref())
+    }
+
+    pub fn response_has_body(&self) -> bool {
+        ContainerHandler::has_body(&self.container.response_headers(), self.container.response_body_bytes(), self.response_stream.as_ref())
+    }
+
+    // Streamed bodies aren't read yet, content-length tells when present
+    fn has_body(headers: &[Header], body: Bytes, stream: Option<&Body>) -> bool {
+        let stream = match stream {
+            Some(val) => val,
+            None => return !body.is_empty()
+        };
+
+        let content_length = headers.iter()
+            .find(|x| x.name == CONTENT_LENGTH.as_str())
+            .and_then(|x| x.value.parse::<u64>().ok());
+
+        match content_length {
+            Some(val) => val > 0,
+            None => !hyper::body::HttpBody::is_end_stream(stream)
+        }
+    }
+
+    pub async fn new(request: Request<Body>, limit: Option<u64>) -> Result<ContainerHandler> {
         let (metadata, body) = request.into_parts();
 
         let request_container = RequestContainerBuilder::new()
@@ -34,17 +130,88 @@ impl ContainerHandler {
             .uri(metadata.uri)
             .version(metadata.version)
             .headers(metadata.headers)
-            .body(hyper::body::to_bytes(body).await?);
+            .body(limits::read_body(body, limit).await?);
 
         Ok(ContainerHandler {
             container: request_container.build(),
-            url,
+            backend: None,
+            request_stream: None,
+            response_stream: None,
+            middleware_body_limit: None,
+            context: HashMap::default(),
+            trailers: HeaderMap::default(),
+            sessions: HashMap::default(),
+            processors: HashMap::default(),
             backend_elapsed: Some(Duration::from_millis(0)),
             timer: Instant::now()
         })
     }
 
+    // Body is not buffered, it is forwarded to the backend as is, unless a middleware replaces it.
+    pub fn streaming(request: Request<Body>, limit: Option<u64>) -> ContainerHandler {
+        let (metadata, body) = request.into_parts();
+
+        let request_container = RequestContainerBuilder::new()
+            .method(metadata.method)
+            .uri(metadata.uri)
+            .version(metadata.version)
+            .headers(metadata.headers);
+
+        ContainerHandler {
+            container: request_container.build(),
+            backend: None,
+            request_stream: Some(limits::limit_stream(body, limit)),
+            response_stream: None,
+            middleware_body_limit: None,
+            context: HashMap::default(),
+            trailers: HeaderMap::default(),
+            sessions: HashMap::default(),
+            processors: HashMap::default(),
+            backend_elapsed: Some(Duration::from_millis(0)),
+            timer: Instant::now()
+        }
+    }
+
+    // Context lives as long as the request, middlewares publish values for the ones after them.
+    // Empty value removes the key.
+    fn merge_context(&mut self, context: &HashMap<String, String>) {
+        for (key, value) in context {
+            match value.is_empty() {
+                true => self.context.remove(key),
+                false => self.context.insert(key.clone(), value.clone())
+            };
+        }
+    }
+
+    // Path and query are replaced independently, an empty query removes it
+    fn rewrite_uri(&mut self, path: &Option<String>, query: &Option<String>) -> Result<()> {
+        if path.is_none() && query.is_none() {
+            return Ok(())
+        }
+
+        let path = path.clone().unwrap_or_else(|| self.container.path());
+        let uri = match query.clone().or_else(|| self.container.query()) {
+            Some(val) if !val.is_empty() => [path, val].join("?"),
+            _ => path
+        };
+
+        self.container.uri_set(Uri::from_str(uri.as_str())?);
+
+        Ok(())
+    }
+
+    // Untouched query is kept as is, so its original encoding is preserved
+    fn edit_query(&mut self, removed: &[String], added: &[QueryParameter]) -> Result<()> {
+        if removed.is_empty() && added.is_empty() {
+            return Ok(())
+        }
+
+        let query = query::edit(self.container.query(), removed, added);
+        self.rewrite_uri(&None, &Some(query.unwrap_or_default()))
+    }
+
     pub fn handle_middleware_request(&mut self, response: &RequestResponse, stop: bool) -> Result<()> {
+        self.merge_context(&response.context);
         self.container.remove_request_headers(&response.removed_headers);
 
         match stop {
@@ -57,8 +224,24 @@ impl ContainerHandler {
             None => ()
         };
 
+        if !stop {
+            if let Some(val) = &response.method {
+                self.container.method_set(Method::from_str(val.as_str())?);
+            }
+
+            if response.backend.is_some() {
+                self.backend = response.backend.clone();
+            }
+
+            self.rewrite_uri(&response.path, &response.query)?;
+            self.edit_query(&response.removed_query_params, &response.added_query_params)?;
+        }
+
         match &response.body {
-            Some(val) => self.container.body_set_string(val.clone()),
+            Some(val) => {
+                if !stop { self.request_stream = None }
+                self.container.body_set_string(val.clone())
+            },
             None => ()
         };
 
@@ -66,8 +249,9 @@ impl ContainerHandler {
     }
 
     pub fn handle_middleware_response(&mut self, response: &ResponseResponse, stop: bool) -> Result<()> {
+        self.merge_context(&response.context);
 
-        // TODO: figure out how to return multiple set-cookie headers
+        // Added headers replace existing ones, multiple set-cookie headers need the v2 AppendHeader mutation
         self.container.remove_response_headers(&response.removed_headers.clone());
         self.container.add_response_headers(&response.added_headers)?;
 
@@ -77,58 +261,261 @@ impl ContainerHandler {
         };
 
         match &response.body {
-            Some(val) => self.container.body_set_string(val.clone()),
-            None => if stop { self.container.body_set_string(String::new()) } else { self.container.body_set_string(self.container.body()?) }
+            Some(val) => {
+                self.response_stream = None;
+                self.container.body_set_string(val.clone())
+            },
+            None => if stop {
+                self.response_stream = None;
+                self.container.body_set_string(String::new())
+            } else {
+                self.container.body_set_string(self.container.body()?)
+            }
         };
 
         Ok(())
     }
 
-    pub async fn handle_response(&mut self, response: Response<Body>) -> Result<()> {
+    pub fn handle_request_answer(&mut self, answer: &RequestAnswer, stop: bool) -> Result<()> {
+        match answer {
+            RequestAnswer::V1(val) => self.handle_middleware_request(val, stop),
+            RequestAnswer::V2(val) => self.handle_middleware_request_v2(val, stop)
+        }
+    }
+
+    pub fn handle_response_answer(&mut self, answer: &ResponseAnswer, stop: bool) -> Result<()> {
+        match answer {
+            ResponseAnswer::V1(val) => self.handle_middleware_response(val, stop),
+            ResponseAnswer::V2(val) => self.handle_middleware_response_v2(val, stop)
+        }
+    }
+
+    pub fn handle_middleware_request_v2(&mut self, response: &RequestResponseV2, stop: bool) -> Result<()> {
+        self.merge_context(&response.context);
+
+        // Mutations of a STOP target the synthetic response, it is always kept apart from the request
+        if stop {
+            self.container.state_set(ContainerState::MiddlewareResponse);
+        }
+
+        self.apply_mutations(&response.mutations, !stop)
+    }
+
+    pub fn handle_middleware_response_v2(&mut self, response: &ResponseResponseV2, stop: bool) -> Result<()> {
+        self.merge_context(&response.context);
+
+        // Same defaults as v1, a stop without mutations is an empty 500
+        if stop {
+            self.container.status_code_set(500);
+            self.container.body_set_string(String::new());
+            self.response_stream = None;
+        }
+
+        self.apply_mutations(&response.mutations, false)
+    }
+
+    // Mutations are applied in order to the current message, routing ones only while the request is still going to the backend
+    fn apply_mutations(&mut self, mutations: &[Mutation], request: bool) -> Result<()> {
+        for mutation in mutations.iter().filter_map(|x| x.mutation.as_ref()) {
+            match mutation {
+                SetHeader(val) => self.container.set_header(val)?,
+                AppendHeader(val) => self.container.append_header(val)?,
+                RemoveHeader(val) => self.container.remove_header(val),
+                RenameHeader(val) => self.container.rename_header(&val.from, &val.to)?,
+                SetStatus(val) => self.container.status_code_set(*val as u16),
+                SetTrailer(val) => {
+                    self.trailers.insert(HeaderName::from_lowercase(val.name.to_lowercase().as_bytes())?, HeaderValue::from_str(&val.value)?);
+                },
+                SetBody(val) => {
+                    match request {
+                        true => self.request_stream = None,
+                        false => self.response_stream = None
+                    };
+                    self.container.body_set_string(val.clone())
+                },
+                SetMethod(val) if request => self.container.method_set(Method::from_str(val.as_str())?),
+                SetPath(val) if request => self.rewrite_uri(&Some(val.clone()), &None)?,
+                SetQuery(val) if request => self.rewrite_uri(&None, &Some(val.clone()))?,
+                SetBackend(val) if request => self.backend = Some(val.clone()),
+                AddQueryParam(val) if request => self.edit_query(&[], std::slice::from_ref(val))?,
+                RemoveQueryParam(val) if request => self.edit_query(std::slice::from_ref(val), &[])?,
+                _ => warn!("Routing mutations are ignored once the request left for the backend")
+            };
+        }
+
+        Ok(())
+    }
+
+    pub async fn handle_response(&mut self, response: Response<Body>, buffer: bool, limit: Option<u64>) -> Result<()> {
         let (metadata, body) = response.into_parts();
 
         self.container.response_headers_set(metadata.headers.to_owned());
         self.container.status_code_set(metadata.status.as_u16());
-        self.container.body_set_bytes(hyper::body::to_bytes(body).await?);
+
+        match buffer {
+            true => self.container.body_set_bytes(limits::read_body(body, limit).await?),
+            false => self.response_stream = Some(limits::limit_stream(body, limit))
+        };
 
         Ok(())
     }
 
-    pub fn into_middleware_request(&mut self) -> Result<RequestRequest> {
-        Ok(RequestRequest {
+    // Only the headers the middleware asked for are sent
+    fn mask_headers(headers: Vec<Header>, middleware: &Middleware) -> Vec<Header> {
+        match middleware.headers() {
+            Some(names) => headers.into_iter().filter(|x| names.contains(&x.name)).collect(),
+            None => headers
+        }
+    }
+
+    // Bodies are left out for middlewares which don't need them
+    fn mask_body(&self, body: Result<String>, enabled: bool) -> Result<(String, bool)> {
+        match enabled {
+            true => Ok(limits::truncate(body?, self.middleware_body_limit)),
+            false => Ok((String::new(), false))
+        }
+    }
+
+    pub fn into_middleware_request(&mut self, middleware: &Middleware) -> Result<RequestRequest> {
+        let (body, body_truncated) = self.mask_body(self.container.request_body(), middleware.request_body())?;
+
+        Ok(self.middleware_request(middleware, body, body_truncated))
+    }
+
+    // Body is left out of the message and returned as is, it is sent in chunks after it
+    pub fn chunked_middleware_request(&self, middleware: &Middleware) -> (RequestRequest, Bytes) {
+        let body = match middleware.request_body() {
+            true => self.container.request_body_bytes(),
+            false => Bytes::new()
+        };
+
+        (self.middleware_request(middleware, String::new(), false), body)
+    }
+
+    fn middleware_request(&self, middleware: &Middleware, body: String, body_truncated: bool) -> RequestRequest {
+        RequestRequest {
             method: self.container.method(),
             uri: self.container.uri(),
-            headers: self.container.headers(),
-            body: self.container.request_body()?
+            headers: ContainerHandler::mask_headers(self.container.request_headers(), middleware),
+            body,
+            body_truncated,
+            context: self.context.clone(),
+            scheme: self.container.scheme(),
+            host: self.container.host(),
+            path: self.container.path(),
+            query_params: query::parse(self.container.query().as_deref())
+        }
+    }
+
+    pub fn into_middleware_error(&mut self, middleware: &Middleware, kind: ErrorKind, message: &str) -> Result<ErrorRequest> {
+        Ok(ErrorRequest {
+            kind: kind as i32,
+            message: message.to_string(),
+            elapsed_ms: self.timer.elapsed().as_millis() as u32,
+            request: Some(self.into_middleware_request(middleware)?)
         })
     }
 
-    pub fn into_middleware_response(&mut self) -> Result<ResponseRequest> {
-        Ok(ResponseRequest {
+    // Default error response, error middlewares work on top of it
+    pub fn error_set(&mut self, status_code: u16, body: &str) {
+        self.container.state_set(ContainerState::MiddlewareResponse);
+        self.container.response_headers_set(HeaderMap::default());
+        self.container.status_code_set(status_code);
+        self.container.body_set_string(body.to_string());
+        self.response_stream = None;
+    }
+
+    pub fn into_middleware_response(&mut self, middleware: &Middleware) -> Result<ResponseRequest> {
+        let request_body = self.mask_body(self.container.request_body(), middleware.request_body())?;
+        let response_body = self.mask_body(self.container.response_body(), middleware.response_body())?;
+
+        Ok(self.middleware_response(middleware, request_body, response_body))
+    }
+
+    // Request body stays inline, the response body is sent in chunks after the message
+    pub fn chunked_middleware_response(&self, middleware: &Middleware) -> Result<(ResponseRequest, Bytes)> {
+        let request_body = self.mask_body(self.container.request_body(), middleware.request_body())?;
+        let body = match middleware.response_body() {
+            true => self.container.response_body_bytes(),
+            false => Bytes::new()
+        };
+
+        Ok((self.middleware_response(middleware, request_body, (String::new(), false)), body))
+    }
+
+    fn middleware_response(&self, middleware: &Middleware, request_body: (String, bool), response_body: (String, bool)) -> ResponseRequest {
+        let (request_body, request_body_truncated) = request_body;
+        let (response_body, response_body_truncated) = response_body;
+
+        ResponseRequest {
             method: self.container.method(),
             uri: self.container.uri(),
-            request_headers: self.container.request_headers(),
-            response_headers: self.container.response_headers(),
-            request_body: self.container.request_body()?,
-            response_body: self.container.response_body()?
-        })
+            request_headers: ContainerHandler::mask_headers(self.container.request_headers(), middleware),
+            response_headers: ContainerHandler::mask_headers(self.container.response_headers(), middleware),
+            request_body,
+            response_body,
+            request_body_truncated,
+            response_body_truncated,
+            context: self.context.clone()
+        }
     }
 
-    pub fn into_request(&mut self) -> Result<Request<Body>> {
+    pub fn into_request(&mut self, backend_url: &str) -> Result<Request<Body>> {
         let mut request_builder = Request::builder()
             .method(Method::from_str(self.container.method().as_str())?)
-            .uri(self.url.clone())
+            .uri([backend_url, self.container.path_and_query().as_str()].join(""))
             .version(self.container.version());
 
         let headers_dict = request_builder.headers_mut().unwrap();
 
+        // Repeated headers keep all their values, they used to collapse to the last one
         for header in self.container.request_headers() {
-            headers_dict.insert(HeaderName::from_lowercase(header.name.to_lowercase().as_bytes())?, HeaderValue::from_str(header.value.as_str())?);
+            headers_dict.append(HeaderName::from_lowercase(header.name.to_lowercase().as_bytes())?, HeaderValue::from_str(header.value.as_str())?);
         }
 
-        headers_dict.remove(CONTENT_LENGTH);
+        match self.request_stream.take() {
+            Some(body) => Ok(request_builder.body(body)?),
+            None => {
+                headers_dict.remove(CONTENT_LENGTH);
 
-        Ok(request_builder.body(self.container.request_body()?.into())?)
+                Ok(request_builder.body(self.container.request_body()?.into())?)
+            }
+        }
+    }
+
+    pub fn grpc_request(&mut self, backend_url: &str) -> Result<Request<Body>> {
+        let mut request = self.into_request(backend_url)?;
+        *request.version_mut() = Version::HTTP_2;
+
+        Ok(request)
+    }
+
+    // Backend response is sent back untouched, so streamed messages and trailers (grpc-status, grpc-message) are kept
+    pub fn passthrough_response(&mut self, response: Response<Body>) -> Result<Response<Body>> {
+        let (mut metadata, body) = response.into_parts();
+
+        metadata.headers.insert(KUBEWARE_TIME_HEADER, HeaderValue::from_str(&self.timer.elapsed().as_millis().to_string())?);
+        metadata.headers.insert(BACKEND_TIME_HEADER, HeaderValue::from_str(&self.backend_elapsed.unwrap().as_millis().to_string())?);
+
+        info!("[{}] {} - {} | {} ms.", self.container.method(), self.container.uri(), metadata.status.as_u16(), self.timer.elapsed().as_millis());
+
+        Ok(Response::from_parts(metadata, body))
+    }
+
+    // Middleware stopped the pipeline, status code and body are translated to grpc-status and grpc-message
+    pub fn grpc_error_response(&mut self) -> Result<Response<Body>> {
+        let mut response = self.into_response()?;
+        let code = grpc::code_from_status_code(self.container.status_code());
+        let message = self.container.body()?;
+
+        *response.status_mut() = hyper::StatusCode::OK;
+        *response.body_mut() = Body::empty();
+
+        let headers_dict = response.headers_mut();
+        headers_dict.insert(CONTENT_TYPE, HeaderValue::from_static(grpc::GRPC_CONTENT_TYPE));
+        grpc::set_status(headers_dict, code, &message)?;
+
+        Ok(response)
     }
 
     pub fn into_response(&mut self) -> Result<Response<Body>> {
@@ -141,14 +528,25 @@ impl ContainerHandler {
         let headers_dict = response.headers_mut().unwrap();
         let headers = self.container.response_headers().to_owned();
 
+        // Same for responses, so every set-cookie of the backend reaches the client
         for header in headers {
-            headers_dict.insert(HeaderName::from_lowercase(header.name.to_lowercase().as_bytes())?, HeaderValue::from_str(header.value.as_str())?);
+            headers_dict.append(HeaderName::from_lowercase(header.name.to_lowercase().as_bytes())?, HeaderValue::from_str(header.value.as_str())?);
         }
 
-        headers_dict.remove(CONTENT_LENGTH);
-
         info!("[{}] {} - {} | {} ms.", self.container.method(), self.container.uri(), self.container.status_code().unwrap_or(500), self.timer.elapsed().as_millis());
 
-        Ok(response.body(self.container.body()?.into())?)
+        let body = match self.response_stream.take() {
+            Some(body) => body,
+            None => {
+                headers_dict.remove(CONTENT_LENGTH);
+                self.container.body()?.into()
+            }
+        };
+
+        if !self.trailers.is_empty() {
+            response = response.extension(Trailers(std::mem::take(&mut self.trailers)));
+        }
+
+        Ok(response.body(body)?)
     }
 }
\ No newline at end of file
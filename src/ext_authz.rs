@@ -0,0 +1,120 @@
+use tonic::transport::Channel;
+use hyper::Uri;
+use std::str::FromStr;
+use crate::envoy_auth::authorization_client::AuthorizationClient;
+use crate::envoy_auth::{CheckRequest, CheckResponse, AttributeContext, HeaderValueOption};
+use crate::envoy_auth::attribute_context::{Request, HttpRequest};
+use crate::envoy_auth::check_response::HttpResponse;
+use crate::kubeware::{RequestRequest, RequestResponseV2, Mutation, Header, QueryParameter, ResponseStatus};
+use crate::kubeware::mutation::Mutation::*;
+use crate::container_handler::{ContainerHandler, RequestAnswer};
+use crate::middleware::Middleware;
+use crate::handler::{self, MiddlewareHandler};
+use async_trait::async_trait;
+
+type GenericError = Box<dyn std::error::Error + Send + Sync>;
+type Result<T> = std::result::Result<T, GenericError>;
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+const DENIED_STATUS_CODE: u32 = 403;
+
+// Request stage only, ext_authz services never see the response
+pub async fn check(connection: &mut AuthorizationClient<Channel>, request: tonic::Request<RequestRequest>) -> Result<RequestAnswer> {
+    let (metadata, message) = (request.metadata().clone(), request.into_inner());
+    let mut request = tonic::Request::new(check_request(message));
+    *request.metadata_mut() = metadata;
+
+    Ok(answer(connection.check(request).await?.into_inner()))
+}
+
+#[async_trait]
+impl MiddlewareHandler for AuthorizationClient<Channel> {
+    async fn request(&self, container: &mut ContainerHandler, middleware: &Middleware) -> Result<RequestAnswer> {
+        let request = handler::call(container.into_middleware_request(middleware)?, middleware)?;
+
+        check(&mut self.clone(), request).await
+    }
+}
+
+// Same shape Envoy sends, path includes the query string and repeated headers are joined with a comma
+fn check_request(message: RequestRequest) -> CheckRequest {
+    let mut headers = std::collections::HashMap::<String, String>::new();
+
+    for Header { name, value } in message.headers {
+        match headers.get_mut(&name) {
+            Some(val) => { val.push(','); val.push_str(&value) },
+            None => { headers.insert(name, value); }
+        };
+    }
+
+    let path = Uri::from_str(&message.uri).ok()
+        .and_then(|x| x.path_and_query().map(|x| x.as_str().to_string()))
+        .unwrap_or(message.path);
+
+    let http = HttpRequest {
+        id: headers.get(REQUEST_ID_HEADER).cloned().unwrap_or_default(),
+        method: message.method,
+        headers,
+        path,
+        host: message.host,
+        scheme: message.scheme,
+        size: message.body.len() as i64,
+        body: message.body,
+        ..Default::default()
+    };
+
+    CheckRequest {
+        attributes: Some(AttributeContext {
+            request: Some(Request { http: Some(http) }),
+            context_extensions: message.context
+        })
+    }
+}
+
+// Answer is translated to v2 mutations, so header append and removal keep their meaning
+fn answer(response: CheckResponse) -> RequestAnswer {
+    let allowed = response.status.map(|x| x.code == 0).unwrap_or(true);
+    let mut mutations = Vec::new();
+
+    match (allowed, response.http_response) {
+        (true, Some(HttpResponse::OkResponse(val))) => {
+            mutations.extend(val.headers.into_iter().filter_map(header_mutation));
+            mutations.extend(val.headers_to_remove.into_iter().map(RemoveHeader));
+
+            for parameter in val.query_parameters_to_set {
+                mutations.push(RemoveQueryParam(parameter.key.clone()));
+                mutations.push(AddQueryParam(QueryParameter { name: parameter.key, value: parameter.value }));
+            }
+
+            mutations.extend(val.query_parameters_to_remove.into_iter().map(RemoveQueryParam));
+
+            if !val.response_headers_to_add.is_empty() {
+                warn!("ext_authz response_headers_to_add is not supported, the headers are dropped");
+            }
+        },
+        (false, Some(HttpResponse::DeniedResponse(val))) => {
+            mutations.push(SetStatus(val.status.map(|x| x.code).filter(|x| *x > 0).unwrap_or(DENIED_STATUS_CODE)));
+            mutations.extend(val.headers.into_iter().filter_map(header_mutation));
+            mutations.push(SetBody(val.body));
+        },
+        (true, _) => (),
+        (false, _) => mutations.push(SetStatus(DENIED_STATUS_CODE))
+    };
+
+    RequestAnswer::V2(RequestResponseV2 {
+        status: match allowed { true => ResponseStatus::Success, false => ResponseStatus::Stop } as i32,
+        mutations: mutations.into_iter().map(|x| Mutation { mutation: Some(x) }).collect(),
+        ..Default::default()
+    })
+}
+
+// Headers overwrite existing values unless append is set
+fn header_mutation(option: HeaderValueOption) -> Option<crate::kubeware::mutation::Mutation> {
+    let header = option.header?;
+    let header = Header { name: header.key, value: header.value };
+
+    match option.append.unwrap_or(false) {
+        true => Some(AppendHeader(header)),
+        false => Some(SetHeader(header))
+    }
+}
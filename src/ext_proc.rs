@@ -0,0 +1,264 @@
+use tonic::transport::Channel;
+use tonic::codec::Streaming;
+use tokio::sync::mpsc;
+use hyper::Uri;
+use std::str::FromStr;
+use crate::envoy_ext_proc::external_processor_client::ExternalProcessorClient;
+use crate::envoy_ext_proc::{ProcessingRequest, ProcessingResponse, HttpHeaders, HttpBody, HeaderMap, HeaderValue};
+use crate::envoy_ext_proc::{CommonResponse, HeaderMutation, ImmediateResponse};
+use crate::envoy_ext_proc::processing_request::Request as Phase;
+use crate::envoy_ext_proc::processing_response::Response as Answer;
+use crate::envoy_ext_proc::common_response::ResponseStatus as CommonStatus;
+use crate::envoy_ext_proc::body_mutation::Mutation as BodyMutation;
+use crate::kubeware::{RequestRequest, RequestResponseV2, ResponseRequest, ResponseResponseV2, Mutation, Header, ResponseStatus};
+use crate::kubeware::mutation::Mutation::*;
+use crate::container_handler::{ContainerHandler, RequestAnswer, ResponseAnswer};
+use crate::middleware::Middleware;
+use crate::handler::MiddlewareHandler;
+use async_trait::async_trait;
+
+type GenericError = Box<dyn std::error::Error + Send + Sync>;
+type Result<T> = std::result::Result<T, GenericError>;
+
+const STREAM_BUFFER: usize = 4;
+
+// Processing stream of one request, shared by both stages like a session
+pub struct Stream {
+    requests: mpsc::Sender<ProcessingRequest>,
+    responses: Streaming<ProcessingResponse>
+}
+
+// Mutations gathered over the phases of a stage, stop is set by an immediate response
+struct Outcome {
+    mutations: Vec<crate::kubeware::mutation::Mutation>,
+    stop: bool
+}
+
+impl Stream {
+    // First phase is queued before opening, so the processor may wait for it before answering
+    async fn open(connection: &mut ExternalProcessorClient<Channel>, phase: Phase) -> Result<Stream> {
+        let (mut requests, receiver) = mpsc::channel(STREAM_BUFFER);
+        requests.send(ProcessingRequest { request: Some(phase) }).await?;
+
+        let responses = connection.process(tonic::Request::new(receiver)).await?.into_inner();
+
+        Ok(Stream { requests, responses })
+    }
+
+    async fn start(stream: Option<Stream>, connection: &mut ExternalProcessorClient<Channel>, phase: Phase) -> Result<Stream> {
+        match stream {
+            Some(mut val) => {
+                val.requests.send(ProcessingRequest { request: Some(phase) }).await?;
+                Ok(val)
+            },
+            None => Stream::open(connection, phase).await
+        }
+    }
+
+    async fn send(&mut self, phase: Phase) -> Result<()> {
+        Ok(self.requests.send(ProcessingRequest { request: Some(phase) }).await?)
+    }
+
+    async fn answer(&mut self) -> Result<Answer> {
+        match self.responses.message().await? {
+            Some(ProcessingResponse { response: Some(val) }) => Ok(val),
+            Some(_) => Err("Processing response is empty".into()),
+            None => Err("Processing stream was closed by the middleware".into())
+        }
+    }
+}
+
+// Headers phase, then the body phase when the body is sent and the processor didn't replace it already
+pub async fn request(stream: Option<Stream>, connection: &mut ExternalProcessorClient<Channel>, message: RequestRequest, end_of_stream: bool) -> Result<(Stream, RequestAnswer)> {
+    let path = Uri::from_str(&message.uri).ok()
+        .and_then(|x| x.path_and_query().map(|x| x.as_str().to_string()))
+        .unwrap_or(message.path);
+
+    let pseudo_headers = vec![
+        (":method", message.method),
+        (":path", path),
+        (":authority", message.host),
+        (":scheme", message.scheme)
+    ];
+
+    let headers = HttpHeaders {
+        headers: Some(header_map(pseudo_headers, message.headers)),
+        end_of_stream
+    };
+
+    let mut stream = Stream::start(stream, connection, Phase::RequestHeaders(headers)).await?;
+    let outcome = phases(&mut stream, message.body, true).await?;
+
+    Ok((stream, RequestAnswer::V2(RequestResponseV2 {
+        status: outcome.status() as i32,
+        mutations: outcome.mutations(),
+        ..Default::default()
+    })))
+}
+
+pub async fn response(stream: Option<Stream>, connection: &mut ExternalProcessorClient<Channel>, message: ResponseRequest, status_code: u16, end_of_stream: bool) -> Result<(Stream, ResponseAnswer)> {
+    let headers = HttpHeaders {
+        headers: Some(header_map(vec![(":status", status_code.to_string())], message.response_headers)),
+        end_of_stream
+    };
+
+    let mut stream = Stream::start(stream, connection, Phase::ResponseHeaders(headers)).await?;
+    let outcome = phases(&mut stream, message.response_body, false).await?;
+
+    Ok((stream, ResponseAnswer::V2(ResponseResponseV2 {
+        status: outcome.status() as i32,
+        mutations: outcome.mutations(),
+        ..Default::default()
+    })))
+}
+
+// Stream is kept in the container between the stages
+#[async_trait]
+impl MiddlewareHandler for ExternalProcessorClient<Channel> {
+    async fn request(&self, container: &mut ContainerHandler, middleware: &Middleware) -> Result<RequestAnswer> {
+        let message = container.into_middleware_request(middleware)?;
+        // The message body is masked when bodies aren't sent, end of stream must come from the real one
+        let end_of_stream = !container.request_has_body();
+        let (stream, answer) = request(container.processor_take(middleware.id()), &mut self.clone(), message, end_of_stream).await?;
+        container.processor_set(middleware.id(), stream);
+
+        Ok(answer)
+    }
+
+    async fn response(&self, container: &mut ContainerHandler, middleware: &Middleware) -> Result<ResponseAnswer> {
+        let message = container.into_middleware_response(middleware)?;
+        let status_code = container.status_code().unwrap_or(500);
+        let end_of_stream = !container.response_has_body();
+        let (stream, answer) = response(container.processor_take(middleware.id()), &mut self.clone(), message, status_code, end_of_stream).await?;
+        container.processor_set(middleware.id(), stream);
+
+        Ok(answer)
+    }
+}
+
+// Headers phase was already sent, its answer decides whether the body phase happens
+async fn phases(stream: &mut Stream, body: String, request: bool) -> Result<Outcome> {
+    let mut outcome = Outcome { mutations: Vec::new(), stop: false };
+
+    let common = match (stream.answer().await?, request) {
+        (Answer::ImmediateResponse(val), _) => return Ok(outcome.immediate(val)),
+        (Answer::RequestHeaders(val), true) | (Answer::ResponseHeaders(val), false) => val.response,
+        _ => return Err("Processor answered the headers phase out of order".into())
+    };
+
+    let replaced = common.as_ref().map(|x| x.status == CommonStatus::ContinueAndReplace as i32).unwrap_or(false);
+    outcome.common(common);
+
+    if body.is_empty() || replaced {
+        return Ok(outcome)
+    }
+
+    let body = HttpBody { body: body.into_bytes(), end_of_stream: true };
+    stream.send(match request { true => Phase::RequestBody(body), false => Phase::ResponseBody(body) }).await?;
+
+    match (stream.answer().await?, request) {
+        (Answer::ImmediateResponse(val), _) => Ok(outcome.immediate(val)),
+        (Answer::RequestBody(val), true) | (Answer::ResponseBody(val), false) => {
+            outcome.common(val.response);
+            Ok(outcome)
+        },
+        _ => Err("Processor answered the body phase out of order".into())
+    }
+}
+
+impl Outcome {
+    fn common(&mut self, response: Option<CommonResponse>) {
+        let response = match response {
+            Some(val) => val,
+            None => return
+        };
+
+        if let Some(val) = response.header_mutation {
+            self.headers(val);
+        }
+
+        match response.body_mutation.and_then(|x| x.mutation) {
+            Some(BodyMutation::Body(val)) => self.mutations.push(SetBody(String::from_utf8_lossy(&val).to_string())),
+            Some(BodyMutation::ClearBody(true)) => self.mutations.push(SetBody(String::new())),
+            _ => ()
+        };
+    }
+
+    // Replaces whatever the earlier phases asked for, as Envoy does
+    fn immediate(mut self, response: ImmediateResponse) -> Outcome {
+        self.mutations.clear();
+
+        if let Some(val) = response.status.map(|x| x.code).filter(|x| *x > 0) {
+            self.mutations.push(SetStatus(val));
+        }
+
+        if let Some(val) = response.headers {
+            self.headers(val);
+        }
+
+        self.mutations.push(SetBody(response.body));
+        self.stop = true;
+        self
+    }
+
+    // Pseudo headers map to the request line and status, the ones without a counterpart are ignored
+    fn headers(&mut self, mutation: HeaderMutation) {
+        for option in mutation.set_headers {
+            let header = match option.header {
+                Some(val) => val,
+                None => continue
+            };
+
+            let value = match header.value.is_empty() {
+                true => String::from_utf8_lossy(&header.raw_value).to_string(),
+                false => header.value
+            };
+
+            match header.key.to_lowercase().as_str() {
+                ":method" => self.mutations.push(SetMethod(value)),
+                ":path" => {
+                    let mut parts = value.splitn(2, '?');
+                    self.mutations.push(SetPath(parts.next().unwrap_or_default().to_string()));
+                    self.mutations.push(SetQuery(parts.next().unwrap_or_default().to_string()));
+                },
+                ":status" => match value.parse::<u32>() {
+                    Ok(val) => self.mutations.push(SetStatus(val)),
+                    Err(_) => warn!("ext_proc :status [{}] is not a status code, it is ignored", value)
+                },
+                name if name.starts_with(':') => warn!("ext_proc {} mutation is not supported, it is ignored", name),
+                _ => {
+                    let header = Header { name: header.key, value };
+
+                    match option.append.unwrap_or(false) {
+                        true => self.mutations.push(AppendHeader(header)),
+                        false => self.mutations.push(SetHeader(header))
+                    }
+                }
+            };
+        }
+
+        self.mutations.extend(mutation.remove_headers.into_iter().filter(|x| !x.starts_with(':')).map(RemoveHeader));
+    }
+
+    fn status(&self) -> ResponseStatus {
+        match self.stop {
+            true => ResponseStatus::Stop,
+            false => ResponseStatus::Success
+        }
+    }
+
+    fn mutations(self) -> Vec<Mutation> {
+        self.mutations.into_iter().map(|x| Mutation { mutation: Some(x) }).collect()
+    }
+}
+
+// Both value and raw_value are filled, older processors read the former and newer ones the latter
+fn header_map(pseudo_headers: Vec<(&str, String)>, headers: Vec<Header>) -> HeaderMap {
+    let headers = pseudo_headers.into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .chain(headers.into_iter().map(|x| (x.name, x.value)))
+        .map(|(key, value)| HeaderValue { key, raw_value: value.clone().into_bytes(), value })
+        .collect();
+
+    HeaderMap { headers }
+}
@@ -0,0 +1,76 @@
+use hyper::{HeaderMap, Response, Body};
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use tonic::Code;
+
+type GenericError = Box<dyn std::error::Error + Send + Sync>;
+type Result<T> = std::result::Result<T, GenericError>;
+
+pub const GRPC_CONTENT_TYPE: &str = "application/grpc";
+pub const GRPC_STATUS_HEADER: &str = "grpc-status";
+pub const GRPC_MESSAGE_HEADER: &str = "grpc-message";
+
+pub fn is_grpc(headers: &HeaderMap) -> bool {
+    match headers.get(CONTENT_TYPE).map(|x| x.to_str()) {
+        Some(Ok(value)) => match value.strip_prefix(GRPC_CONTENT_TYPE) {
+            // application/grpc, application/grpc+proto, application/grpc;charset=..., not application/grpc-web
+            Some(rest) => rest.is_empty() || rest.starts_with('+') || rest.starts_with(';'),
+            None => false
+        },
+        _ => false
+    }
+}
+
+// https://github.com/grpc/grpc/blob/master/doc/http-grpc-status-mapping.md, except 400 which it maps to INTERNAL:
+// a middleware STOP with 400 rejects the caller's input, so INVALID_ARGUMENT is kept on purpose
+pub fn code_from_status_code(status_code: Option<u16>) -> Code {
+    match status_code {
+        Some(400) => Code::InvalidArgument,
+        Some(401) => Code::Unauthenticated,
+        Some(403) => Code::PermissionDenied,
+        Some(404) => Code::Unimplemented,
+        Some(429) | Some(502) | Some(503) | Some(504) => Code::Unavailable,
+        _ => Code::Unknown
+    }
+}
+
+// grpc-message is percent encoded, everything outside of printable ASCII and '%' itself has to be escaped
+pub fn encode_message(message: &str) -> String {
+    let mut encoded = String::with_capacity(message.len());
+
+    for byte in message.bytes() {
+        match byte {
+            b'%' => encoded.push_str("%25"),
+            0x20..=0x7E => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte))
+        }
+    }
+
+    encoded
+}
+
+// Trailers-only response, which is how gRPC servers report an error before any message is sent.
+pub fn error_response(code: Code, message: &str) -> Result<Response<Body>> {
+    let mut response = Response::builder()
+        .status(200)
+        .header(CONTENT_TYPE, HeaderValue::from_static(GRPC_CONTENT_TYPE));
+
+    let headers_dict = response.headers_mut().unwrap();
+    set_status(headers_dict, code, message)?;
+
+    Ok(response.body(Body::empty())?)
+}
+
+// Sets grpc-status and grpc-message, unless the middleware already provided its own grpc-status.
+pub fn set_status(headers: &mut HeaderMap, code: Code, message: &str) -> Result<()> {
+    if headers.contains_key(GRPC_STATUS_HEADER) {
+        return Ok(())
+    }
+
+    headers.insert(GRPC_STATUS_HEADER, HeaderValue::from_str(&(code as i32).to_string())?);
+
+    if !message.is_empty() {
+        headers.insert(GRPC_MESSAGE_HEADER, HeaderValue::from_str(&encode_message(message))?);
+    }
+
+    Ok(())
+}
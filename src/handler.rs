@@ -0,0 +1,110 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use tonic::transport::Channel;
+use tonic::metadata::MetadataValue;
+use crate::kubeware::middleware_client::MiddlewareClient;
+use crate::kubeware::{RequestResponse, ResponseResponse, ResponseStatus, ErrorKind};
+use crate::container_handler::{ContainerHandler, RequestAnswer, ResponseAnswer};
+use crate::middleware::Middleware;
+use crate::session;
+use crate::body_chunks;
+
+type GenericError = Box<dyn std::error::Error + Send + Sync>;
+type Result<T> = std::result::Result<T, GenericError>;
+
+const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+// What a configured middleware runs, remote transports and in-process middlewares alike.
+// Stages which aren't implemented answer CONTINUE, only gRPC middlewares have an error stage.
+#[async_trait]
+pub trait MiddlewareHandler: Send + Sync {
+    async fn request(&self, _container: &mut ContainerHandler, _middleware: &Middleware) -> Result<RequestAnswer> {
+        Ok(RequestAnswer::V1(RequestResponse { status: ResponseStatus::Continue as i32, ..Default::default() }))
+    }
+
+    async fn response(&self, _container: &mut ContainerHandler, _middleware: &Middleware) -> Result<ResponseAnswer> {
+        Ok(ResponseAnswer::V1(ResponseResponse { status: ResponseStatus::Continue as i32, ..Default::default() }))
+    }
+
+    async fn error(&self, _container: &mut ContainerHandler, _middleware: &Middleware, _kind: ErrorKind, _message: &str) -> Result<ResponseResponse> {
+        Err("Error stage is only supported by gRPC middlewares".into())
+    }
+}
+
+pub fn shared<T: MiddlewareHandler + 'static>(handler: T) -> Arc<dyn MiddlewareHandler> {
+    Arc::new(handler)
+}
+
+// gRPC message with the middleware timeout as its deadline
+pub fn call<T>(message: T, middleware: &Middleware) -> Result<tonic::Request<T>> {
+    let timeout = [middleware.timeout().as_millis().to_string(), "m".to_string()].join("");
+    let mut request = tonic::Request::new(message);
+    let metadata = request.metadata_mut();
+    metadata.insert(GRPC_TIMEOUT_HEADER, MetadataValue::from_str(timeout.as_str())?);
+
+    Ok(request)
+}
+
+// Clients are cheap to clone, calls need them mutable
+#[async_trait]
+impl MiddlewareHandler for MiddlewareClient<Channel> {
+    async fn request(&self, container: &mut ContainerHandler, middleware: &Middleware) -> Result<RequestAnswer> {
+        let connection = &mut self.clone();
+
+        if middleware.stream_body() {
+            let (message, body) = container.chunked_middleware_request(middleware);
+            let request = call(body_chunks::request(message, body), middleware)?;
+
+            return Ok(RequestAnswer::V1(connection.handle_request_stream(request).await?.into_inner()))
+        }
+
+        let message = container.into_middleware_request(middleware)?;
+
+        if middleware.session() {
+            let (session, answer) = session::request(container.session_take(middleware.id()), connection, message).await?;
+            container.session_set(middleware.id(), session);
+
+            return Ok(answer)
+        }
+
+        let request = call(message, middleware)?;
+
+        match middleware.v2() {
+            true => Ok(RequestAnswer::V2(connection.handle_request_v2(request).await?.into_inner())),
+            false => Ok(RequestAnswer::V1(connection.handle_request(request).await?.into_inner()))
+        }
+    }
+
+    async fn response(&self, container: &mut ContainerHandler, middleware: &Middleware) -> Result<ResponseAnswer> {
+        let connection = &mut self.clone();
+
+        if middleware.stream_body() {
+            let (message, body) = container.chunked_middleware_response(middleware)?;
+            let request = call(body_chunks::response(message, body), middleware)?;
+
+            return Ok(ResponseAnswer::V1(connection.handle_response_stream(request).await?.into_inner()))
+        }
+
+        let message = container.into_middleware_response(middleware)?;
+
+        if middleware.session() {
+            let (session, answer) = session::response(container.session_take(middleware.id()), connection, message).await?;
+            container.session_set(middleware.id(), session);
+
+            return Ok(answer)
+        }
+
+        let request = call(message, middleware)?;
+
+        match middleware.v2() {
+            true => Ok(ResponseAnswer::V2(connection.handle_response_v2(request).await?.into_inner())),
+            false => Ok(ResponseAnswer::V1(connection.handle_response(request).await?.into_inner()))
+        }
+    }
+
+    async fn error(&self, container: &mut ContainerHandler, middleware: &Middleware, kind: ErrorKind, message: &str) -> Result<ResponseResponse> {
+        let request = call(container.into_middleware_error(middleware, kind, message)?, middleware)?;
+
+        Ok(self.clone().handle_error(request).await?.into_inner())
+    }
+}
@@ -31,7 +31,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -40,7 +41,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
         
@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod tests {
+    use crate::integration_tests::{setup_kubeware, setup_backend};
+    use crate::config::Config;
+    use crate::middlewares::Middlewares;
+    use crate::registry::Registry;
+    use crate::handler::{self, MiddlewareHandler};
+    use hyper::{Body, Client, Request, Response};
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{Ordering};
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        type = "request_id"
+
+        [[middleware]]
+        type = "headers"
+        options = { request = { "X-Env" = "prod" }, remove_response = ["x-powered-by"] }
+    "#;
+
+    struct Noop;
+
+    impl MiddlewareHandler for Noop {}
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_builtin_middlewares_are_configured_they_run_in_the_pipeline() -> Result<()> {
+        // Arrange
+        let request_id = Arc::new(Mutex::new(String::new()));
+        let cloned_request_id = Arc::clone(&request_id);
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(move |req| {
+            assert_eq!("prod", req.headers().get("x-env").unwrap());
+            *cloned_request_id.lock().unwrap() = req.headers().get("x-request-id").unwrap().to_str().unwrap().to_string();
+
+            Response::builder()
+                .header("x-powered-by", "backend")
+                .body(Body::from("OK"))
+                .unwrap()
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(200, res.status().as_u16());
+        assert!(res.headers().get("x-powered-by").is_none());
+        assert_eq!(24, request_id.lock().unwrap().len());
+        assert_eq!(request_id.lock().unwrap().as_str(), res.headers().get("x-request-id").unwrap());
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn when_type_is_registered_middleware_is_built_from_registry() -> Result<()> {
+        // Arrange
+        let config: Config = toml::from_str(r#"
+            [backend]
+            url = "http://127.0.0.1:17001"
+
+            [[middleware]]
+            type = "noop"
+            response = false
+        "#)?;
+
+        let registry = Registry::new().register("noop", |_config| Ok(handler::shared(Noop)));
+        let mut middlewares = Middlewares::with_registry(&config, registry);
+
+        // Act
+        middlewares.insert(&config.middlewares[0]).await?;
+
+        // Assert
+        assert_eq!(1, middlewares.request().len());
+        assert_eq!(0, middlewares.response().len());
+        assert!(middlewares.all()[0].resolved());
+        assert_eq!("noop", middlewares.all()[0].url());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn when_type_is_unknown_startup_fails() -> Result<()> {
+        // Arrange
+        let config: Config = toml::from_str(r#"
+            [backend]
+            url = "http://127.0.0.1:17001"
+
+            [[middleware]]
+            type = "missing"
+        "#)?;
+
+        let mut middlewares = Middlewares::with_config(&config);
+
+        // Act
+        let result = middlewares.insert(&config.middlewares[0]).await;
+
+        // Assert
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn when_entries_share_a_url_each_reconnects_with_its_own_config() -> Result<()> {
+        // Arrange
+        let config: Config = toml::from_str(r#"
+            [backend]
+            url = "http://127.0.0.1:17001"
+
+            [[middleware]]
+            url = "http://127.0.0.1:17099"
+            timeout_ms = 100
+
+            [[middleware]]
+            url = "http://127.0.0.1:17099"
+            timeout_ms = 200
+        "#)?;
+
+        let mut middlewares = Middlewares::with_config(&config);
+
+        for middleware in &config.middlewares {
+            middlewares.insert(middleware).await?;
+        }
+
+        // Act
+        let middlewares = middlewares.ensure_connected().await?;
+
+        // Assert
+        assert_eq!(2, middlewares.all().len());
+        assert_eq!(100, middlewares.all()[0].timeout().as_millis());
+        assert_eq!(200, middlewares.all()[1].timeout().as_millis());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn when_builtin_enables_grpc_only_options_startup_fails() -> Result<()> {
+        // Arrange
+        let config: Config = toml::from_str(r#"
+            [backend]
+            url = "http://127.0.0.1:17001"
+
+            [[middleware]]
+            type = "request_id"
+            error = true
+            session = true
+        "#)?;
+
+        let mut middlewares = Middlewares::with_config(&config);
+
+        // Act
+        let result = middlewares.insert(&config.middlewares[0]).await;
+
+        // Assert
+        assert_eq!("Middleware [request_id] is built-in, which doesn't support session, error", result.unwrap_err().to_string());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+#[cfg(test)]
+mod tests {
+    use tonic::{Request as TonicRequest, Response as TonicResponse};
+    use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse, ResponseStatus};
+    use crate::integration_tests::{setup_middleware, setup_kubeware, setup_backend2, BackendResponse};
+    use hyper::{Body, Client, Request, Response};
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{Ordering};
+    use async_trait::async_trait;
+    use crate::config::Config;
+    use crate::middlewares::Middlewares;
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+        max_middleware_body_bytes = 4
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = true
+        stream_body = true
+    "#;
+
+    // Echoes the request body back
+    #[derive(Clone)]
+    pub struct Backend;
+
+    #[async_trait]
+    impl BackendResponse for Backend {
+        async fn handle(&mut self, request: Request<Body>) -> Response<Body> {
+            Response::new(Body::from(hyper::body::to_bytes(request.into_body()).await.unwrap()))
+        }
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_middleware_streams_bodies_it_receives_them_in_full() -> Result<()> {
+        // Arrange
+        let request_body = Arc::new(Mutex::new(None));
+        let response_body = Arc::new(Mutex::new(None));
+        let cloned_request_body = Arc::clone(&request_body);
+        let cloned_response_body = Arc::clone(&response_body);
+
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |req: TonicRequest<RequestRequest>| {
+                let data = req.into_inner();
+                *cloned_request_body.lock().unwrap() = Some((data.body.len(), data.body_truncated));
+
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Success as i32,
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |req: TonicRequest<ResponseRequest>| {
+                let data = req.into_inner();
+                *cloned_response_body.lock().unwrap() = Some((data.response_body.len(), data.request_body));
+
+                TonicResponse::new(ResponseResponse {
+                    status: ResponseStatus::Continue as i32,
+                    ..Default::default()
+                })
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend2(Backend).await?;
+
+        // Act
+        let body = "a".repeat(200_000);
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/upload")
+            .method("POST")
+            .body(Body::from(body.clone()))
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(200, res.status().as_u16());
+        assert_eq!(body.len(), hyper::body::to_bytes(res.into_body()).await?.len());
+        assert_eq!(Some((200_000, false)), *request_body.lock().unwrap());
+        assert_eq!(Some((200_000, "aaaa".to_string())), *response_body.lock().unwrap());
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(1, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn when_stream_body_is_combined_with_session_or_v2_startup_fails() -> Result<()> {
+        // Arrange
+        let config: Config = toml::from_str(r#"
+            [backend]
+            url = "http://127.0.0.1:17001"
+
+            [[middleware]]
+            url = "http://127.0.0.1:17002"
+            request = true
+            stream_body = true
+            session = true
+
+            [[middleware]]
+            url = "http://127.0.0.1:17002"
+            request = true
+            stream_body = true
+            api_version = 2
+        "#)?;
+
+        let mut middlewares = Middlewares::with_config(&config);
+
+        // Act
+        let session = middlewares.insert(&config.middlewares[0]).await;
+        let v2 = middlewares.insert(&config.middlewares[1]).await;
+
+        // Assert
+        assert!(session.is_err());
+        assert!(v2.is_err());
+
+        Ok(())
+    }
+}
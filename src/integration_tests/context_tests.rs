@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod tests {
+    use tonic::{Request as TonicRequest, Response as TonicResponse};
+    use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse, ResponseStatus};
+    use crate::integration_tests::{setup_middleware, setup_kubeware, setup_backend};
+    use hyper::{Body, Client, Request, Response};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{Ordering};
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = true
+    "#;
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_request_stage_sets_context_response_stage_receives_it() -> Result<()> {
+        // Arrange
+        let request_context = Arc::new(Mutex::new(None));
+        let response_context = Arc::new(Mutex::new(None));
+        let cloned_request_context = Arc::clone(&request_context);
+        let cloned_response_context = Arc::clone(&response_context);
+
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |req: TonicRequest<RequestRequest>| {
+                *cloned_request_context.lock().unwrap() = Some(req.into_inner().context);
+
+                let mut context = HashMap::new();
+                context.insert("user-id".to_string(), "42".to_string());
+
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Success as i32,
+                    context,
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |req: TonicRequest<ResponseRequest>| {
+                *cloned_response_context.lock().unwrap() = Some(req.into_inner().context);
+                TonicResponse::new(ResponseResponse::default())
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|req| {
+            assert!(req.headers().get("user-id").is_none());
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        let mut expected = HashMap::new();
+        expected.insert("user-id".to_string(), "42".to_string());
+
+        assert_eq!(200, res.status().as_u16());
+        assert_eq!(Some(HashMap::new()), *request_context.lock().unwrap());
+        assert_eq!(Some(expected), *response_context.lock().unwrap());
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(1, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod tests {
+    use tonic::{Request as TonicRequest, Response as TonicResponse};
+    use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse, ResponseStatus, DescribeResponse};
+    use crate::integration_tests::{setup_described_middleware, setup_kubeware, setup_backend};
+    use hyper::{Body, Client, Request, Response};
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{Ordering};
+    use crate::PROTOCOL_VERSION;
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+    "#;
+
+    fn description(protocol_version: u32) -> DescribeResponse {
+        DescribeResponse {
+            name: "auth".to_string(),
+            protocol_version,
+            request: true,
+            response: false,
+            request_body: false,
+            response_body: false,
+            headers: vec!["X-Keep".to_string()],
+            timeout_ms: Some(1000),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_stages_are_omitted_in_config_middleware_description_is_used() -> Result<()> {
+        // Arrange
+        let middleware_request = Arc::new(Mutex::new(None));
+        let cloned_request = Arc::clone(&middleware_request);
+
+        let (middleware_tx, request_counter, response_counter) = setup_described_middleware(
+            Box::new(move |req: TonicRequest<RequestRequest>| {
+                let data = req.into_inner();
+                let headers = data.headers.into_iter().map(|x| x.name).collect::<Vec<String>>();
+                *cloned_request.lock().unwrap() = Some((headers, data.body));
+
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Success as i32,
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            }),
+            description(PROTOCOL_VERSION)).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .method("POST")
+            .header("x-keep", "1")
+            .header("x-drop", "1")
+            .body(Body::from("Real body !"))
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(200, res.status().as_u16());
+        assert_eq!(Some((vec!["x-keep".to_string()], String::new())), *middleware_request.lock().unwrap());
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(0, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_middleware_requires_newer_protocol_kubeware_refuses_to_start() -> Result<()> {
+        // Arrange
+        let (middleware_tx, request_counter, _) = setup_described_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse::default())
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            }),
+            description(PROTOCOL_VERSION + 1)).await?;
+
+        // Act
+        let kubeware = setup_kubeware(CONFIG).await;
+
+        // Assert
+        assert!(kubeware.is_err());
+        assert_eq!(0, request_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+}
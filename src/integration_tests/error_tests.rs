@@ -0,0 +1,189 @@
+#[cfg(test)]
+mod tests {
+    use tonic::{Request as TonicRequest, Response as TonicResponse};
+    use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse, ResponseStatus, ErrorRequest, ErrorKind};
+    use crate::integration_tests::{setup_error_middleware, setup_kubeware, setup_backend};
+    use hyper::{Body, Client, Request, Response};
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{Ordering};
+    use std::time::Duration;
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17009"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = false
+        response = false
+        error = true
+    "#;
+
+    const TIMEOUT_CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        timeout_ms = 100
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = false
+        response = false
+        error = true
+    "#;
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_backend_is_unreachable_error_middleware_replaces_response() -> Result<()> {
+        // Arrange
+        let error_request = Arc::new(Mutex::new(None));
+        let cloned_request = Arc::clone(&error_request);
+
+        let (middleware_tx, request_counter, response_counter) = setup_error_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse::default())
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            }),
+            Box::new(move |req: TonicRequest<ErrorRequest>| {
+                let data = req.into_inner();
+                *cloned_request.lock().unwrap() = Some((data.kind, data.request.unwrap().uri));
+
+                TonicResponse::new(ResponseResponse {
+                    status: ResponseStatus::Success as i32,
+                    body: Some("Branded error page".to_string()),
+                    status_code: Some(503),
+                    ..Default::default()
+                })
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/page?id=1")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        assert_eq!(503, parts.status.as_u16());
+        assert_eq!("Branded error page", hyper::body::to_bytes(body).await?);
+        assert_eq!(Some((ErrorKind::BackendUnavailable as i32, "/page?id=1".to_string())), *error_request.lock().unwrap());
+        assert_eq!(0, request_counter.load(Ordering::Relaxed));
+        assert_eq!(0, response_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_backend_times_out_and_error_middleware_continues_default_response_is_returned() -> Result<()> {
+        // Arrange
+        let error_kind = Arc::new(Mutex::new(None));
+        let cloned_kind = Arc::clone(&error_kind);
+
+        let (middleware_tx, _, _) = setup_error_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse::default())
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            }),
+            Box::new(move |req: TonicRequest<ErrorRequest>| {
+                *cloned_kind.lock().unwrap() = Some(req.into_inner().kind);
+
+                TonicResponse::new(ResponseResponse {
+                    status: ResponseStatus::Continue as i32,
+                    body: Some("Ignored".to_string()),
+                    ..Default::default()
+                })
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(TIMEOUT_CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            std::thread::sleep(Duration::from_millis(300));
+            Response::new(Body::from("Too late"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        assert_eq!(504, parts.status.as_u16());
+        assert_eq!("Gateway Timeout", hyper::body::to_bytes(body).await?);
+        assert_eq!(Some(ErrorKind::BackendTimeout as i32), *error_kind.lock().unwrap());
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_only_error_middleware_wants_request_body_it_receives_it() -> Result<()> {
+        // Arrange
+        let error_body = Arc::new(Mutex::new(None));
+        let cloned_body = Arc::clone(&error_body);
+
+        let (middleware_tx, _, _) = setup_error_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse::default())
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            }),
+            Box::new(move |req: TonicRequest<ErrorRequest>| {
+                *cloned_body.lock().unwrap() = Some(req.into_inner().request.unwrap().body);
+
+                TonicResponse::new(ResponseResponse {
+                    status: ResponseStatus::Continue as i32,
+                    ..Default::default()
+                })
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(&format!("{}request_body = true", CONFIG)).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/orders")
+            .method("POST")
+            .body(Body::from("Real body !"))
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(502, res.status().as_u16());
+        assert_eq!(Some("Real body !".to_string()), *error_body.lock().unwrap());
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+}
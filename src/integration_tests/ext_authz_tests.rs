@@ -0,0 +1,133 @@
+#[cfg(test)]
+mod tests {
+    use tonic::{Request as TonicRequest, Response as TonicResponse};
+    use crate::envoy_auth::{CheckRequest, CheckResponse, Status, OkHttpResponse, DeniedHttpResponse, HttpStatus, HeaderValueOption, HeaderValue, QueryParameter};
+    use crate::envoy_auth::check_response::HttpResponse;
+    use crate::integration_tests::{setup_ext_authz, setup_kubeware, setup_backend};
+    use hyper::{Body, Client, Request, Response};
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{Ordering};
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        protocol = "ext_authz"
+    "#;
+
+    fn header(key: &str, value: &str, append: Option<bool>) -> HeaderValueOption {
+        HeaderValueOption {
+            header: Some(HeaderValue { key: key.to_string(), value: value.to_string() }),
+            append
+        }
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_ext_authz_allows_request_header_and_query_mutations_are_applied() -> Result<()> {
+        // Arrange
+        let check_request = Arc::new(Mutex::new(None));
+        let cloned_check_request = Arc::clone(&check_request);
+
+        let (middleware_tx, check_counter) = setup_ext_authz(Box::new(move |req: TonicRequest<CheckRequest>| {
+            let http = req.into_inner().attributes.unwrap().request.unwrap().http.unwrap();
+            *cloned_check_request.lock().unwrap() = Some((http.method, http.path, http.headers.get("authorization").cloned()));
+
+            TonicResponse::new(CheckResponse {
+                status: Some(Status { code: 0, message: String::new() }),
+                http_response: Some(HttpResponse::OkResponse(OkHttpResponse {
+                    headers: vec![header("x-user", "alice", None), header("x-role", "admin", Some(true))],
+                    headers_to_remove: vec!["authorization".to_string()],
+                    query_parameters_to_set: vec![QueryParameter { key: "tenant".to_string(), value: "acme".to_string() }],
+                    ..Default::default()
+                }))
+            })
+        })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|req| {
+            let roles = req.headers().get_all("x-role").iter().map(|x| x.to_str().unwrap()).collect::<Vec<&str>>();
+
+            assert_eq!("alice", req.headers().get("x-user").unwrap());
+            assert_eq!(vec!["user", "admin"], roles);
+            assert!(req.headers().get("authorization").is_none());
+            assert_eq!("/items?tenant=acme", req.uri().to_string());
+
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/items?tenant=other")
+            .header("authorization", "Bearer token")
+            .header("x-role", "user")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(200, res.status().as_u16());
+        assert_eq!("OK", hyper::body::to_bytes(res.into_body()).await?);
+        assert_eq!(Some(("GET".to_string(), "/items?tenant=other".to_string(), Some("Bearer token".to_string()))), *check_request.lock().unwrap());
+        assert_eq!(1, check_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_ext_authz_denies_request_denied_response_is_returned() -> Result<()> {
+        // Arrange
+        let (middleware_tx, check_counter) = setup_ext_authz(Box::new(move |_req: TonicRequest<CheckRequest>| {
+            TonicResponse::new(CheckResponse {
+                status: Some(Status { code: 7, message: "denied".to_string() }),
+                http_response: Some(HttpResponse::DeniedResponse(DeniedHttpResponse {
+                    status: Some(HttpStatus { code: 401 }),
+                    headers: vec![header("www-authenticate", "Bearer", None)],
+                    body: "Unauthorized".to_string()
+                }))
+            })
+        })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/items")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        assert_eq!(401, parts.status.as_u16());
+        assert_eq!("Bearer", parts.headers.get("www-authenticate").unwrap());
+        assert_eq!("Unauthorized", hyper::body::to_bytes(body).await?);
+        assert_eq!(1, check_counter.load(Ordering::Relaxed));
+        assert_eq!(0, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,249 @@
+#[cfg(test)]
+mod tests {
+    use crate::envoy_ext_proc::{ProcessingRequest, ProcessingResponse, HeadersResponse, BodyResponse, CommonResponse, ImmediateResponse, HttpStatus, HeaderMutation, HeaderValueOption, HeaderValue, BodyMutation, HeaderMap};
+    use crate::envoy_ext_proc::processing_request::Request as Phase;
+    use crate::envoy_ext_proc::processing_response::Response as Answer;
+    use crate::envoy_ext_proc::body_mutation::Mutation;
+    use crate::integration_tests::{setup_ext_proc, setup_kubeware, setup_backend};
+    use crate::config::Config;
+    use crate::middlewares::Middlewares;
+    use hyper::{Body, Client, Request, Response};
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{Ordering};
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        protocol = "ext_proc"
+        request_body = true
+    "#;
+
+    // Newer processors only fill raw_value
+    fn header(key: &str, value: &str) -> HeaderValueOption {
+        HeaderValueOption {
+            header: Some(HeaderValue { key: key.to_string(), raw_value: value.as_bytes().to_vec(), ..Default::default() }),
+            append: None
+        }
+    }
+
+    fn header_value(headers: &Option<HeaderMap>, key: &str) -> Option<String> {
+        headers.as_ref()?.headers.iter().find(|x| x.key == key).map(|x| x.value.clone())
+    }
+
+    fn common(header_mutation: Option<HeaderMutation>, body: Option<&str>) -> Option<CommonResponse> {
+        Some(CommonResponse {
+            header_mutation,
+            body_mutation: body.map(|x| BodyMutation { mutation: Some(Mutation::Body(x.as_bytes().to_vec())) }),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_ext_proc_mutates_each_phase_request_and_response_are_changed() -> Result<()> {
+        // Arrange
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let cloned_seen = Arc::clone(&seen);
+
+        let (middleware_tx, stream_counter, message_counter) = setup_ext_proc(Arc::new(move |req: &ProcessingRequest| {
+            let answer = match req.request.as_ref().unwrap() {
+                Phase::RequestHeaders(val) => {
+                    cloned_seen.lock().unwrap().push(header_value(&val.headers, ":path").unwrap());
+
+                    Answer::RequestHeaders(HeadersResponse {
+                        response: common(Some(HeaderMutation {
+                            set_headers: vec![header("x-user", "alice"), header(":path", "/v2/items?page=2")],
+                            ..Default::default()
+                        }), None)
+                    })
+                },
+                Phase::RequestBody(val) => {
+                    cloned_seen.lock().unwrap().push(String::from_utf8(val.body.clone()).unwrap());
+
+                    Answer::RequestBody(BodyResponse { response: common(None, Some("Replaced request")) })
+                },
+                Phase::ResponseHeaders(val) => {
+                    cloned_seen.lock().unwrap().push(header_value(&val.headers, ":status").unwrap());
+
+                    Answer::ResponseHeaders(HeadersResponse {
+                        response: common(Some(HeaderMutation {
+                            set_headers: vec![header("x-processed", "yes"), header(":status", "201")],
+                            remove_headers: vec!["x-backend".to_string()]
+                        }), None)
+                    })
+                },
+                Phase::ResponseBody(_) => panic!("Response body is not sent by default")
+            };
+
+            ProcessingResponse { response: Some(answer) }
+        })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|req| {
+            assert_eq!("alice", req.headers().get("x-user").unwrap());
+            assert_eq!("/v2/items?page=2", req.uri().to_string());
+
+            Response::builder()
+                .header("x-backend", "1")
+                .body(req.into_body())
+                .unwrap()
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/items?page=1")
+            .method("POST")
+            .body(Body::from("Real body !"))
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        assert_eq!(201, parts.status.as_u16());
+        assert_eq!("yes", parts.headers.get("x-processed").unwrap());
+        assert!(parts.headers.get("x-backend").is_none());
+        assert_eq!("Replaced request", hyper::body::to_bytes(body).await?);
+        assert_eq!(vec!["/items?page=1", "Real body !", "200"], *seen.lock().unwrap());
+        assert_eq!(1, stream_counter.load(Ordering::Relaxed));
+        assert_eq!(3, message_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_ext_proc_sends_immediate_response_backend_is_not_called() -> Result<()> {
+        // Arrange
+        let (middleware_tx, stream_counter, message_counter) = setup_ext_proc(Arc::new(|_req: &ProcessingRequest| {
+            ProcessingResponse {
+                response: Some(Answer::ImmediateResponse(ImmediateResponse {
+                    status: Some(HttpStatus { code: 403 }),
+                    headers: Some(HeaderMutation { set_headers: vec![header("x-reason", "denied")], ..Default::default() }),
+                    body: "Forbidden".to_string(),
+                    ..Default::default()
+                }))
+            }
+        })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/admin")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        assert_eq!(403, parts.status.as_u16());
+        assert_eq!("denied", parts.headers.get("x-reason").unwrap());
+        assert_eq!("Forbidden", hyper::body::to_bytes(body).await?);
+        assert_eq!(1, stream_counter.load(Ordering::Relaxed));
+        assert_eq!(1, message_counter.load(Ordering::Relaxed));
+        assert_eq!(0, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_bodies_are_not_sent_end_of_stream_still_tells_they_exist() -> Result<()> {
+        // Arrange
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let cloned_seen = Arc::clone(&seen);
+
+        let (middleware_tx, _stream_counter, _message_counter) = setup_ext_proc(Arc::new(move |req: &ProcessingRequest| {
+            let answer = match req.request.as_ref().unwrap() {
+                Phase::RequestHeaders(val) => {
+                    cloned_seen.lock().unwrap().push(val.end_of_stream);
+
+                    Answer::RequestHeaders(HeadersResponse { response: None })
+                },
+                Phase::ResponseHeaders(val) => {
+                    cloned_seen.lock().unwrap().push(val.end_of_stream);
+
+                    Answer::ResponseHeaders(HeadersResponse { response: None })
+                },
+                _ => panic!("Bodies are not sent")
+            };
+
+            ProcessingResponse { response: Some(answer) }
+        })).await?;
+
+        let kubeware_tx = setup_kubeware(r#"
+            ip = "127.0.0.1"
+            port = 17000
+
+            [backend]
+            url = "http://127.0.0.1:17001"
+            version = "HTTP"
+
+            [[middleware]]
+            url = "http://127.0.0.1:17002"
+            protocol = "ext_proc"
+        "#).await?;
+
+        let (backend_tx, _backend_counter) = setup_backend(|req| {
+            Response::new(req.into_body())
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/items")
+            .method("POST")
+            .body(Body::from("Real body !"))
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let body = hyper::body::to_bytes(res.into_body()).await?;
+
+        // Assert
+        assert_eq!("Real body !", body);
+        assert_eq!(vec![false, false], *seen.lock().unwrap());
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn when_ext_proc_enables_grpc_only_options_startup_fails() -> Result<()> {
+        // Arrange
+        let config: Config = toml::from_str(&format!("{}api_version = 2", CONFIG))?;
+        let mut middlewares = Middlewares::with_config(&config);
+
+        // Act
+        let result = middlewares.insert(&config.middlewares[0]).await;
+
+        // Assert
+        assert!(result.unwrap_err().to_string().contains("doesn't support api_version"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tests {
+    use tonic::{Request as TonicRequest, Response as TonicResponse};
+    use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse, ResponseStatus, Header};
+    use crate::integration_tests::{setup_middleware, setup_kubeware, setup_backend};
+    use hyper::{Body, Client, Request, Response};
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{Ordering};
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Same middleware twice, once masked and once receiving everything
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = true
+        request_body = false
+        response_body = false
+        headers = ["X-Keep"]
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = true
+    "#;
+
+    fn header_names(headers: Vec<Header>) -> Vec<String> {
+        headers.into_iter().map(|x| x.name).collect()
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_middleware_has_field_mask_it_receives_only_selected_headers_and_no_bodies() -> Result<()> {
+        // Arrange
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let responses = Arc::new(Mutex::new(Vec::new()));
+        let cloned_requests = Arc::clone(&requests);
+        let cloned_responses = Arc::clone(&responses);
+
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |req: TonicRequest<RequestRequest>| {
+                let data = req.into_inner();
+                cloned_requests.lock().unwrap().push((header_names(data.headers), data.body));
+
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Success as i32,
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |req: TonicRequest<ResponseRequest>| {
+                let data = req.into_inner();
+                cloned_responses.lock().unwrap().push((header_names(data.request_headers), data.request_body, data.response_body));
+
+                TonicResponse::new(ResponseResponse::default())
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("Backend body"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .method("POST")
+            .header("x-keep", "1")
+            .header("x-drop", "1")
+            .body(Body::from("Real body !"))
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        let requests = requests.lock().unwrap();
+        let responses = responses.lock().unwrap();
+
+        assert_eq!(200, res.status().as_u16());
+        assert_eq!((vec!["x-keep".to_string()], String::new()), requests[0]);
+        assert!(requests[1].0.contains(&"x-drop".to_string()));
+        assert_eq!("Real body !", requests[1].1);
+        assert_eq!((vec!["x-keep".to_string()], String::new(), String::new()), responses[0]);
+        assert_eq!(("Real body !".to_string(), "Backend body".to_string()), (responses[1].1.clone(), responses[1].2.clone()));
+        assert_eq!(2, request_counter.load(Ordering::Relaxed));
+        assert_eq!(2, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,247 @@
+#[cfg(test)]
+mod tests {
+    use tonic::{Request as TonicRequest, Response as TonicResponse, Code};
+    use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse, ResponseStatus};
+    use crate::kubeware::middleware_client::MiddlewareClient;
+    use crate::integration_tests::{setup_middleware, setup_kubeware, setup_grpc_backend, setup_backend};
+    use hyper::{Body, Client, Request, Response};
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{Ordering};
+    use crate::KUBEWARE_TIME_HEADER;
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = true
+    "#;
+
+    fn grpc_request() -> TonicRequest<RequestRequest> {
+        TonicRequest::new(RequestRequest {
+            method: "GET".to_string(),
+            uri: "/".to_string(),
+            headers: Vec::default(),
+            body: "Real body !".to_string(),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_sending_grpc_request_it_is_proxied_and_middleware_receives_metadata_only() -> Result<()> {
+        // Arrange
+        let middleware_body = Arc::new(Mutex::new(None));
+        let middleware_content_type = Arc::new(Mutex::new(None));
+        let cloned_body = Arc::clone(&middleware_body);
+        let cloned_content_type = Arc::clone(&middleware_content_type);
+
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |req: TonicRequest<RequestRequest>| {
+                let data = req.into_inner();
+                *cloned_body.lock().unwrap() = Some(data.body);
+                *cloned_content_type.lock().unwrap() = data.headers.into_iter()
+                    .find(|x| x.name == "content-type")
+                    .map(|x| x.value);
+
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Success as i32,
+                    added_headers: Vec::default(),
+                    removed_headers: Vec::default(),
+                    body: None,
+                    status_code: None,
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse {
+                    status: ResponseStatus::Success as i32,
+                    added_headers: Vec::default(),
+                    removed_headers: Vec::default(),
+                    body: None,
+                    status_code: None,
+                    ..Default::default()
+                })
+            })).await?;
+
+        let (backend_tx, backend_counter, _) = setup_grpc_backend(
+            Box::new(move |req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Success as i32,
+                    added_headers: Vec::default(),
+                    removed_headers: Vec::default(),
+                    body: Some(format!("Backend got {}", req.into_inner().body)),
+                    status_code: None,
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+
+        // Act
+        let mut client = MiddlewareClient::connect("http://127.0.0.1:17000").await?;
+        let response = client.handle_request(grpc_request()).await?;
+
+        // Assert
+        assert!(response.metadata().get(KUBEWARE_TIME_HEADER).is_some());
+        assert_eq!(Some("Backend got Real body !".to_string()), response.into_inner().body);
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(0, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+        assert_eq!(Some(String::new()), *middleware_body.lock().unwrap());
+        assert_eq!(Some("application/grpc".to_string()), *middleware_content_type.lock().unwrap());
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_sending_grpc_request_on_request_stop_grpc_status_is_returned() -> Result<()> {
+        // Arrange
+        let (middleware_tx, request_counter, _) = setup_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Stop as i32,
+                    added_headers: Vec::default(),
+                    removed_headers: Vec::default(),
+                    body: Some("Not allowed ✗".to_string()),
+                    status_code: Some(403),
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            })).await?;
+
+        let (backend_tx, backend_counter, _) = setup_grpc_backend(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse::default())
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+
+        // Act
+        let mut client = MiddlewareClient::connect("http://127.0.0.1:17000").await?;
+        let status = client.handle_request(grpc_request()).await.unwrap_err();
+
+        // Assert
+        assert_eq!(Code::PermissionDenied, status.code());
+        assert_eq!("Not allowed ✗", status.message());
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(0, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_sending_grpc_request_and_middleware_not_reachable_unavailable_is_returned() -> Result<()> {
+        // Arrange
+        let (backend_tx, backend_counter, _) = setup_grpc_backend(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse::default())
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+
+        // Act
+        let mut client = MiddlewareClient::connect("http://127.0.0.1:17000").await?;
+        let status = client.handle_request(grpc_request()).await.unwrap_err();
+
+        // Assert
+        assert_eq!(Code::Unavailable, status.code());
+        assert_eq!(0, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_sending_grpc_web_request_it_is_handled_as_http() -> Result<()> {
+        // Arrange
+        let middleware_body = Arc::new(Mutex::new(String::new()));
+        let cloned_body = Arc::clone(&middleware_body);
+
+        let (middleware_tx, request_counter, _) = setup_middleware(
+            Box::new(move |req: TonicRequest<RequestRequest>| {
+                *cloned_body.lock().unwrap() = req.into_inner().body;
+
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Continue as i32,
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            })).await?;
+
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        let kubeware_tx = setup_kubeware(r#"
+            ip = "127.0.0.1"
+            port = 17000
+
+            [backend]
+            url = "http://127.0.0.1:17001"
+            version = "HTTP"
+
+            [[middleware]]
+            url = "http://127.0.0.1:17002"
+            request = true
+            response = false
+            request_body = true
+        "#).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .method("POST")
+            .header("content-type", "application/grpc-web")
+            .body(Body::from("Real body !"))
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(200, res.status().as_u16());
+        assert_eq!("Real body !", middleware_body.lock().unwrap().as_str());
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::middlewares::Middlewares;
+    use crate::layer::KubewareLayer;
+    use crate::registry::Registry;
+    use crate::handler::{self, MiddlewareHandler};
+    use crate::middleware::Middleware;
+    use crate::container_handler::{ContainerHandler, ResponseAnswer};
+    use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse, ResponseResponseV2, ResponseStatus, Mutation, Header};
+    use crate::kubeware::mutation::Mutation::SetTrailer;
+    use crate::integration_tests::setup_middleware;
+    use async_trait::async_trait;
+    use tonic::{Request as TonicRequest, Response as TonicResponse};
+    use hyper::{Body, Request, Response};
+    use hyper::body::HttpBody;
+    use hyper::service::service_fn;
+    use tower_layer::Layer;
+    use tower_service::Service;
+    use std::convert::Infallible;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    type GenericError = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = std::result::Result<T, GenericError>;
+
+    struct Checksum;
+
+    #[async_trait]
+    impl MiddlewareHandler for Checksum {
+        async fn response(&self, _container: &mut ContainerHandler, _middleware: &Middleware) -> Result<ResponseAnswer> {
+            let trailer = SetTrailer(Header { name: "x-checksum".to_string(), value: "abc".to_string() });
+
+            Ok(ResponseAnswer::V2(ResponseResponseV2 {
+                status: ResponseStatus::Success as i32,
+                mutations: vec![Mutation { mutation: Some(trailer) }],
+                ..Default::default()
+            }))
+        }
+    }
+
+    async fn layer(config: &str) -> Result<KubewareLayer> {
+        let config: Config = toml::from_str(config)?;
+        let mut middlewares = Middlewares::with_config(&config);
+
+        for middleware in &config.middlewares {
+            middlewares.insert(middleware).await?;
+        }
+
+        Ok(KubewareLayer::new(middlewares, config))
+    }
+
+    #[tokio::test]
+    async fn when_service_is_wrapped_middlewares_run_around_it() -> Result<()> {
+        // Arrange
+        let layer = layer(r#"
+            [backend]
+            url = "http://127.0.0.1:17001"
+
+            [[middleware]]
+            type = "headers"
+            options = { request = { "X-Env" = "prod" }, remove_response = ["x-powered-by"] }
+        "#).await?;
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let cloned_counter = Arc::clone(&counter);
+
+        let mut service = layer.layer(service_fn(move |req: Request<Body>| {
+            cloned_counter.fetch_add(1, Ordering::Relaxed);
+
+            assert_eq!("/hello?name=kubeware", req.uri().to_string());
+            assert_eq!("prod", req.headers().get("x-env").unwrap());
+
+            async {
+                Ok::<_, Infallible>(Response::builder()
+                    .header("x-powered-by", "service")
+                    .body(Body::from("OK"))
+                    .unwrap())
+            }
+        }));
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/hello?name=kubeware")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = service.call(req).await?;
+
+        // Assert
+        assert_eq!(200, res.status().as_u16());
+        assert!(res.headers().get("x-powered-by").is_none());
+        assert_eq!("OK", hyper::body::to_bytes(res.into_body()).await?);
+        assert_eq!(1, counter.load(Ordering::Relaxed));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn when_middleware_stops_request_service_is_not_called() -> Result<()> {
+        // Arrange
+        let path = std::env::temp_dir().join("kubeware_layer_stop.rhai");
+        std::fs::write(&path, r#"
+            fn request(req) {
+                #{ status: "STOP", statusCode: 401, body: "Unauthorized" }
+            }
+        "#)?;
+
+        let layer = layer(&format!(r#"
+            [backend]
+            url = "http://127.0.0.1:17001"
+
+            [[middleware]]
+            url = "{}"
+            protocol = "script"
+        "#, path.to_string_lossy())).await?;
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let cloned_counter = Arc::clone(&counter);
+
+        let mut service = layer.layer(service_fn(move |_req: Request<Body>| {
+            cloned_counter.fetch_add(1, Ordering::Relaxed);
+
+            async { Ok::<_, Infallible>(Response::new(Body::from("OK"))) }
+        }));
+
+        // Act
+        let res = service.call(Request::new(Body::empty())).await?;
+
+        // Assert
+        assert_eq!(401, res.status().as_u16());
+        assert_eq!("Unauthorized", hyper::body::to_bytes(res.into_body()).await?);
+        assert_eq!(0, counter.load(Ordering::Relaxed));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn when_service_fails_502_is_returned() -> Result<()> {
+        // Arrange
+        let layer = layer(r#"
+            [backend]
+            url = "http://127.0.0.1:17001"
+        "#).await?;
+
+        let mut service = layer.layer(service_fn(|_req: Request<Body>| async {
+            Err::<Response<Body>, GenericError>("Service is down".into())
+        }));
+
+        // Act
+        let res = service.call(Request::new(Body::empty())).await?;
+
+        // Assert
+        assert_eq!(502, res.status().as_u16());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn when_middleware_sets_trailer_layered_service_returns_it() -> Result<()> {
+        // Arrange
+        let config: Config = toml::from_str(r#"
+            [backend]
+            url = "http://127.0.0.1:17001"
+
+            [[middleware]]
+            type = "checksum"
+        "#)?;
+
+        let registry = Registry::new().register("checksum", |_config| Ok(handler::shared(Checksum)));
+        let mut middlewares = Middlewares::with_registry(&config, registry);
+        middlewares.insert(&config.middlewares[0]).await?;
+
+        let mut service = KubewareLayer::new(middlewares, config).layer(service_fn(|_req: Request<Body>| async {
+            Ok::<_, Infallible>(Response::new(Body::from("OK")))
+        }));
+
+        // Act
+        let res = service.call(Request::new(Body::empty())).await?;
+        let mut body = res.into_body();
+
+        let mut data = Vec::new();
+        while let Some(chunk) = body.data().await {
+            data.extend_from_slice(&chunk?);
+        }
+
+        let trailers = body.trailers().await?;
+
+        // Assert
+        assert_eq!(b"OK".to_vec(), data);
+        assert_eq!(Some("abc"), trailers.as_ref().and_then(|x| x.get("x-checksum")).map(|x| x.to_str().unwrap()));
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_middleware_was_unreachable_it_is_reconnected_on_first_use() -> Result<()> {
+        // Arrange
+        let layer = layer(r#"
+            [backend]
+            url = "http://127.0.0.1:17001"
+
+            [[middleware]]
+            url = "http://127.0.0.1:17002"
+            request = true
+            response = true
+        "#).await?;
+
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(|_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse { status: ResponseStatus::Continue as i32, ..Default::default() })
+            }),
+            Box::new(|_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse { status: ResponseStatus::Continue as i32, ..Default::default() })
+            })).await?;
+
+        let mut service = layer.layer(service_fn(|_req: Request<Body>| async {
+            Ok::<_, Infallible>(Response::new(Body::from("OK")))
+        }));
+
+        // Act
+        let res = service.call(Request::new(Body::empty())).await?;
+
+        // Assert
+        assert_eq!(200, res.status().as_u16());
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(1, response_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn when_backend_is_not_configured_service_is_the_backend() -> Result<()> {
+        // Arrange
+        let layer = layer(r#"
+            [[middleware]]
+            type = "headers"
+            options = { request = { "X-Env" = "prod" } }
+        "#).await?;
+
+        let mut service = layer.layer(service_fn(|req: Request<Body>| async move {
+            assert_eq!("prod", req.headers().get("x-env").unwrap());
+
+            Ok::<_, Infallible>(Response::new(Body::from("OK")))
+        }));
+
+        // Act
+        let res = service.call(Request::new(Body::empty())).await?;
+
+        // Assert
+        assert_eq!(200, res.status().as_u16());
+        assert_eq!("OK", hyper::body::to_bytes(res.into_body()).await?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,266 @@
+#[cfg(test)]
+mod tests {
+    use tonic::{Request as TonicRequest, Response as TonicResponse};
+    use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse, ResponseStatus};
+    use crate::integration_tests::{setup_middleware, setup_kubeware, setup_backend, setup_backend2, BackendResponse};
+    use hyper::{Body, Client, Request, Response};
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{Ordering};
+    use async_trait::async_trait;
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+        max_request_body_bytes = 10
+        max_response_body_bytes = 10
+        max_middleware_body_bytes = 4
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = true
+
+        [[route]]
+        path = "/upload"
+        max_request_body_bytes = 100
+    "#;
+
+    fn request_response() -> TonicResponse<RequestResponse> {
+        TonicResponse::new(RequestResponse {
+            status: ResponseStatus::Success as i32,
+            added_headers: Vec::default(),
+            removed_headers: Vec::default(),
+            body: None,
+            status_code: None,
+            ..Default::default()
+        })
+    }
+
+    fn response_response() -> TonicResponse<ResponseResponse> {
+        TonicResponse::new(ResponseResponse {
+            status: ResponseStatus::Success as i32,
+            added_headers: Vec::default(),
+            removed_headers: Vec::default(),
+            body: None,
+            status_code: None,
+            ..Default::default()
+        })
+    }
+
+    #[derive(Clone)]
+    pub struct Backend {
+        pub body: Arc<Mutex<String>>
+    }
+
+    #[async_trait]
+    impl BackendResponse for Backend {
+        async fn handle(&mut self, request: Request<Body>) -> Response<Body> {
+            let full_body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+            *self.body.lock().unwrap() = std::str::from_utf8(full_body.as_ref()).unwrap().to_string();
+
+            Response::new(Body::from("OK"))
+        }
+    }
+
+    // Reads the whole body, whether or not it arrives
+    #[derive(Clone)]
+    pub struct Draining;
+
+    #[async_trait]
+    impl BackendResponse for Draining {
+        async fn handle(&mut self, request: Request<Body>) -> Response<Body> {
+            let _ = hyper::body::to_bytes(request.into_body()).await;
+
+            Response::new(Body::from("OK"))
+        }
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_request_body_exceeds_limit_413_is_returned() -> Result<()> {
+        // Arrange
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| request_response()),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| response_response())).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let (mut sender, chunked_body) = Body::channel();
+        tokio::spawn(async move {
+            let _ = sender.send_data("Real body !".into()).await;
+            let _ = sender.send_data("Real body !".into()).await;
+        });
+
+        let with_length = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .method("POST")
+            .body(Body::from("Real body !"))
+            .unwrap();
+        let chunked = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .method("POST")
+            .body(chunked_body)
+            .unwrap();
+
+        let with_length = Client::new().request(with_length).await?;
+        let chunked = Client::new().request(chunked).await?;
+
+        // Assert
+        assert_eq!(413, with_length.status().as_u16());
+        assert_eq!(413, chunked.status().as_u16());
+        assert_eq!("Payload Too Large", hyper::body::to_bytes(chunked.into_body()).await?);
+        assert_eq!(0, request_counter.load(Ordering::Relaxed));
+        assert_eq!(0, response_counter.load(Ordering::Relaxed));
+        assert_eq!(0, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_route_has_higher_limit_request_body_is_accepted_and_truncated_for_middleware() -> Result<()> {
+        // Arrange
+        let middleware_body = Arc::new(Mutex::new(None));
+        let cloned_body = Arc::clone(&middleware_body);
+
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |req: TonicRequest<RequestRequest>| {
+                let data = req.into_inner();
+                *cloned_body.lock().unwrap() = Some((data.body, data.body_truncated));
+                request_response()
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| response_response())).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let backend = Backend { body: Arc::new(Mutex::new(String::default())) };
+        let backend_body = Arc::clone(&backend.body);
+        let (backend_tx, backend_counter) = setup_backend2(backend).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/upload/file")
+            .method("POST")
+            .body(Body::from("Real body !"))
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(200, res.status().as_u16());
+        assert_eq!("Real body !", *backend_body.lock().unwrap());
+        assert_eq!(Some(("Real".to_string(), true)), *middleware_body.lock().unwrap());
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(1, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_response_body_exceeds_limit_502_is_returned() -> Result<()> {
+        // Arrange
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| request_response()),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| response_response())).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("Backend body is too large"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/upload")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        assert_eq!(502, parts.status.as_u16());
+        assert_eq!("Bad Gateway", hyper::body::to_bytes(body).await?);
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(0, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_streamed_request_body_exceeds_limit_413_is_returned() -> Result<()> {
+        // Arrange
+        let (middleware_tx, request_counter, _response_counter) = setup_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| request_response()),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| response_response())).await?;
+
+        let kubeware_tx = setup_kubeware(r#"
+            ip = "127.0.0.1"
+            port = 17000
+            max_request_body_bytes = 10
+
+            [backend]
+            url = "http://127.0.0.1:17001"
+            version = "HTTP"
+
+            [[middleware]]
+            url = "http://127.0.0.1:17002"
+            request = true
+            response = true
+            request_body = false
+            response_body = false
+        "#).await?;
+
+        let (backend_tx, _backend_counter) = setup_backend2(Draining).await?;
+
+        // Act
+        let (mut sender, chunked_body) = Body::channel();
+        tokio::spawn(async move {
+            let _ = sender.send_data("Real body !".into()).await;
+            let _ = sender.send_data("Real body !".into()).await;
+        });
+
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .method("POST")
+            .body(chunked_body)
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(413, res.status().as_u16());
+        assert_eq!("Payload Too Large", hyper::body::to_bytes(res.into_body()).await?);
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+}
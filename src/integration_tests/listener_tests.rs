@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod tests {
+    use crate::integration_tests::{setup_kubeware, setup_backend};
+    use hyper::{Body, Client, Request, Response, Version};
+    use std::sync::atomic::{Ordering};
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = false
+        response = false
+    "#;
+
+    const HTTP1_CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [listener]
+        version = "HTTP"
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = false
+        response = false
+    "#;
+
+    const HTTP2_CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [listener]
+        version = "HTTP2"
+        http2_max_concurrent_streams = 10
+        http2_initial_stream_window_size = 1048576
+        http2_initial_connection_window_size = 1048576
+        http2_keep_alive_interval_ms = 1000
+        http2_keep_alive_timeout_ms = 500
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = false
+        response = false
+    "#;
+
+    fn request() -> Request<Body> {
+        Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_listener_is_not_configured_http1_and_h2c_are_accepted() -> Result<()> {
+        // Arrange
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|req| {
+            assert_eq!(Version::HTTP_11, req.version());
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let http1 = Client::new().request(request()).await?;
+        let http2 = Client::builder().http2_only(true).build_http().request(request()).await?;
+
+        // Assert
+        assert_eq!(Version::HTTP_11, http1.version());
+        assert_eq!(200, http1.status().as_u16());
+        assert_eq!(Version::HTTP_2, http2.version());
+        assert_eq!(200, http2.status().as_u16());
+        assert_eq!("OK", hyper::body::to_bytes(http2.into_body()).await?);
+        assert_eq!(2, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_listener_is_http2_only_h2c_is_accepted_and_http1_is_rejected() -> Result<()> {
+        // Arrange
+        let kubeware_tx = setup_kubeware(HTTP2_CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let http2 = Client::builder().http2_only(true).build_http().request(request()).await?;
+        let http1 = Client::new().request(request()).await;
+
+        // Assert
+        assert_eq!(Version::HTTP_2, http2.version());
+        assert_eq!(200, http2.status().as_u16());
+        assert!(http1.is_err());
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_listener_is_http1_only_h2c_is_rejected() -> Result<()> {
+        // Arrange
+        let kubeware_tx = setup_kubeware(HTTP1_CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let http1 = Client::new().request(request()).await?;
+        let http2 = Client::builder().http2_only(true).build_http().request(request()).await;
+
+        // Assert
+        assert_eq!(200, http1.status().as_u16());
+        assert!(http2.is_err());
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+
+        Ok(())
+    }
+}
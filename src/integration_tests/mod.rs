@@ -2,40 +2,87 @@ use std::convert::Into;
 use futures::channel::oneshot;
 use std::sync::{Arc, Mutex};
 use crate::tower_service::Builder;
-use crate::config::{HttpVersion, Config};
+use crate::config::Config;
 use crate::middlewares::Middlewares;
 use std::env::set_var;
 use crate::{RUST_LOG, LOOPBACK, PORT};
+use crate::listener;
+use crate::shutdown::Shutdown;
+use crate::upstream::Clients;
+use futures::future::{self, Either};
 
 use std::str;
 use async_trait::async_trait;
 
 use hyper::service::{make_service_fn, service_fn};
-use hyper::{Body, Client, Request, Response, Server};
+use hyper::{Body, Request, Response, Server};
 use std::net::ToSocketAddrs;
+use std::time::Duration;
 use oneshot::Sender;
 
-use tonic::{transport::Server as TonicServer, Request as TonicRequest, Response as TonicResponse, Status};
-use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse};
+use tonic::{transport::Server as TonicServer, Request as TonicRequest, Response as TonicResponse, Status, Streaming};
+use tokio::sync::mpsc;
+use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse, DescribeRequest, DescribeResponse, ErrorRequest, RequestResponseV2, ResponseResponseV2, SessionEvent, SessionDecision, RequestStreamMessage, ResponseStreamMessage};
+use crate::kubeware::{request_stream_message, response_stream_message};
+use crate::kubeware::session_event::Event;
 use crate::kubeware::middleware_server::{Middleware, MiddlewareServer};
+use crate::envoy_auth::{CheckRequest, CheckResponse};
+use crate::envoy_auth::authorization_server::{Authorization, AuthorizationServer};
+use crate::envoy_ext_proc::{ProcessingRequest, ProcessingResponse};
+use crate::envoy_ext_proc::external_processor_server::{ExternalProcessor, ExternalProcessorServer};
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 type BootstrapResult<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 type RequestFn = Box<dyn Fn(TonicRequest<RequestRequest>) -> TonicResponse<RequestResponse> + Send + 'static + Sync>;
 type ResponseFn = Box<dyn Fn(TonicRequest<ResponseRequest>) -> TonicResponse<ResponseResponse> + Send + 'static + Sync>;
+type ErrorFn = Box<dyn Fn(TonicRequest<ErrorRequest>) -> TonicResponse<ResponseResponse> + Send + 'static + Sync>;
+type RequestV2Fn = Box<dyn Fn(TonicRequest<RequestRequest>) -> TonicResponse<RequestResponseV2> + Send + 'static + Sync>;
+type ResponseV2Fn = Box<dyn Fn(TonicRequest<ResponseRequest>) -> TonicResponse<ResponseResponseV2> + Send + 'static + Sync>;
+type CheckFn = Box<dyn Fn(TonicRequest<CheckRequest>) -> TonicResponse<CheckResponse> + Send + 'static + Sync>;
+// Called with all events of the session so far, once the body of the latest one is complete
+type SessionFn = Arc<dyn Fn(&[SessionEvent]) -> SessionDecision + Send + 'static + Sync>;
+// Called once per phase, every message of the stream is answered
+type ProcessFn = Arc<dyn Fn(&ProcessingRequest) -> ProcessingResponse + Send + 'static + Sync>;
 
 // Tests
 mod basic_tests;
+mod builtin_tests;
+mod chunked_tests;
+mod context_tests;
+mod describe_tests;
+mod error_tests;
+mod ext_authz_tests;
+mod ext_proc_tests;
+mod field_mask_tests;
+mod grpc_tests;
+mod layer_tests;
+mod limits_tests;
+mod listener_tests;
+mod pipeline_tests;
+mod streaming_tests;
 mod request_tests;
+mod session_tests;
 mod response_tests;
+mod script_tests;
+mod sdk_tests;
+mod shutdown_tests;
 mod timeout_tests;
+mod wasm_tests;
+mod webhook_tests;
+mod v2_tests;
 
 pub struct MiddlewareService
 {
     request_fn: RequestFn,
     response_fn: ResponseFn,
+    error_fn: Option<ErrorFn>,
+    request_v2_fn: Option<RequestV2Fn>,
+    response_v2_fn: Option<ResponseV2Fn>,
+    session_fn: Option<SessionFn>,
+    description: Option<DescribeResponse>,
     request_counter: Arc<AtomicUsize>,
-    response_counter: Arc<AtomicUsize>
+    response_counter: Arc<AtomicUsize>,
+    session_counter: Arc<AtomicUsize>
 }
 
 impl MiddlewareService
@@ -44,11 +91,38 @@ impl MiddlewareService
         MiddlewareService {
             request_fn: request,
             response_fn: response,
+            error_fn: None,
+            request_v2_fn: None,
+            response_v2_fn: None,
+            session_fn: None,
+            description: None,
             request_counter: Arc::new(AtomicUsize::new(0)),
-            response_counter: Arc::new(AtomicUsize::new(0))
+            response_counter: Arc::new(AtomicUsize::new(0)),
+            session_counter: Arc::new(AtomicUsize::new(0))
         }
     }
 
+    fn with_error(mut self, error: Option<ErrorFn>) -> MiddlewareService {
+        self.error_fn = error;
+        self
+    }
+
+    fn with_v2(mut self, request: Option<RequestV2Fn>, response: Option<ResponseV2Fn>) -> MiddlewareService {
+        self.request_v2_fn = request;
+        self.response_v2_fn = response;
+        self
+    }
+
+    fn with_session(mut self, session: Option<SessionFn>) -> MiddlewareService {
+        self.session_fn = session;
+        self
+    }
+
+    fn with_description(mut self, description: Option<DescribeResponse>) -> MiddlewareService {
+        self.description = description;
+        self
+    }
+
     fn request_counter(&self) -> Arc<AtomicUsize> {
         Arc::clone(&self.request_counter)
     }
@@ -56,6 +130,10 @@ impl MiddlewareService
     fn response_counter(&self) -> Arc<AtomicUsize>  {
         Arc::clone(&self.response_counter)
     }
+
+    fn session_counter(&self) -> Arc<AtomicUsize>  {
+        Arc::clone(&self.session_counter)
+    }
  }
 
 #[tonic::async_trait]
@@ -77,6 +155,147 @@ impl Middleware for MiddlewareService {
         let _ = self.response_counter.fetch_add(1, Ordering::Relaxed);
         Ok((self.response_fn)(request))
     }
+
+    async fn handle_request_v2(
+        &self,
+        request: TonicRequest<RequestRequest>,
+    ) -> Result<TonicResponse<RequestResponseV2>, Status> {
+        match &self.request_v2_fn {
+            Some(val) => {
+                let _ = self.request_counter.fetch_add(1, Ordering::Relaxed);
+                Ok((val)(request))
+            },
+            None => Err(Status::unimplemented("HandleRequestV2 is not implemented"))
+        }
+    }
+
+    async fn handle_response_v2(
+        &self,
+        request: TonicRequest<ResponseRequest>,
+    ) -> Result<TonicResponse<ResponseResponseV2>, Status> {
+        match &self.response_v2_fn {
+            Some(val) => {
+                let _ = self.response_counter.fetch_add(1, Ordering::Relaxed);
+                Ok((val)(request))
+            },
+            None => Err(Status::unimplemented("HandleResponseV2 is not implemented"))
+        }
+    }
+
+    // Streamed messages are put back together, so the same closures serve both variants
+    async fn handle_request_stream(
+        &self,
+        request: TonicRequest<Streaming<RequestStreamMessage>>,
+    ) -> Result<TonicResponse<RequestResponse>, Status> {
+        let metadata = request.metadata().clone();
+        let mut inbound = request.into_inner();
+        let mut message = RequestRequest::default();
+        let mut body = Vec::new();
+
+        while let Some(item) = inbound.message().await? {
+            match item.message {
+                Some(request_stream_message::Message::Request(val)) => message = val,
+                Some(request_stream_message::Message::Chunk(val)) => body.extend(val.data),
+                None => ()
+            }
+        }
+
+        message.body = String::from_utf8_lossy(&body).to_string();
+
+        let mut request = TonicRequest::new(message);
+        *request.metadata_mut() = metadata;
+
+        self.handle_request(request).await
+    }
+
+    async fn handle_response_stream(
+        &self,
+        request: TonicRequest<Streaming<ResponseStreamMessage>>,
+    ) -> Result<TonicResponse<ResponseResponse>, Status> {
+        let metadata = request.metadata().clone();
+        let mut inbound = request.into_inner();
+        let mut message = ResponseRequest::default();
+        let mut body = Vec::new();
+
+        while let Some(item) = inbound.message().await? {
+            match item.message {
+                Some(response_stream_message::Message::Response(val)) => message = val,
+                Some(response_stream_message::Message::Chunk(val)) => body.extend(val.data),
+                None => ()
+            }
+        }
+
+        message.response_body = String::from_utf8_lossy(&body).to_string();
+
+        let mut request = TonicRequest::new(message);
+        *request.metadata_mut() = metadata;
+
+        self.handle_response(request).await
+    }
+
+    type SessionStream = mpsc::Receiver<Result<SessionDecision, Status>>;
+
+    async fn session(
+        &self,
+        request: TonicRequest<Streaming<SessionEvent>>,
+    ) -> Result<TonicResponse<Self::SessionStream>, Status> {
+        let session_fn = match &self.session_fn {
+            Some(val) => Arc::clone(val),
+            None => return Err(Status::unimplemented("Session is not implemented"))
+        };
+
+        let request_counter = Arc::clone(&self.request_counter);
+        let response_counter = Arc::clone(&self.response_counter);
+        let _ = self.session_counter.fetch_add(1, Ordering::Relaxed);
+
+        let mut inbound = request.into_inner();
+        let (mut tx, rx) = mpsc::channel(4);
+
+        tokio::spawn(async move {
+            let mut events = Vec::new();
+
+            while let Ok(Some(event)) = inbound.message().await {
+                match &event.event {
+                    Some(Event::Request(_)) => { let _ = request_counter.fetch_add(1, Ordering::Relaxed); },
+                    Some(Event::Response(_)) => { let _ = response_counter.fetch_add(1, Ordering::Relaxed); },
+                    _ => ()
+                };
+
+                let last = match &event.event {
+                    Some(Event::Chunk(val)) => val.last,
+                    _ => false
+                };
+
+                events.push(event);
+
+                if last && tx.send(Ok((session_fn)(&events))).await.is_err() {
+                    break
+                }
+            }
+        });
+
+        Ok(TonicResponse::new(rx))
+    }
+
+    async fn handle_error(
+        &self,
+        request: TonicRequest<ErrorRequest>,
+    ) -> Result<TonicResponse<ResponseResponse>, Status> {
+        match &self.error_fn {
+            Some(val) => Ok((val)(request)),
+            None => Err(Status::unimplemented("HandleError is not implemented"))
+        }
+    }
+
+    async fn describe(
+        &self,
+        _request: TonicRequest<DescribeRequest>,
+    ) -> Result<TonicResponse<DescribeResponse>, Status> {
+        match &self.description {
+            Some(val) => Ok(TonicResponse::new(val.clone())),
+            None => Err(Status::unimplemented("Describe is not implemented"))
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -98,27 +317,23 @@ async fn setup_kubeware (config: &str) -> BootstrapResult<Sender<()>> {
         middlewares.insert(middleware).await?;
     }
 
-    let http_client = match &config.backend.version {
-        Some(version) => match version {
-            HttpVersion::HTTP => Client::new(),
-            HttpVersion::HTTP2 => Client::builder().http2_only(true).build_http()
-        },
-        _ => Client::new()
-    };
-
     let (tx, rx) = oneshot::channel::<()>();
+    let shutdown = Shutdown::with_config(&config);
 
-    let server = Server::bind(&address).serve(Builder {
-        http_client,
+    let (stop_accepting, drain_deadline) = shutdown.start(async move {
+        rx.await.ok();
+    });
+
+    let server = listener::bind(&address, &config.listener).serve(Builder {
+        upstream: Arc::new(Clients::new()),
         config,
         mutex: Mutex::new(false),
+        ready: shutdown.ready(),
         middlewares: Arc::new(middlewares)
-    }).with_graceful_shutdown(async move {
-        rx.await.ok();
-    });
+    }).with_graceful_shutdown(stop_accepting);
 
     tokio::task::spawn(async move {
-        if let Err(e) = server.await {
+        if let Either::Left((Err(e), _)) = future::select(Box::pin(server), Box::pin(drain_deadline)).await {
             error!("server error: {}", e);
         }
     });
@@ -166,8 +381,19 @@ async fn setup_backend<F> (closure: F) -> BootstrapResult<(Sender<()>, Arc<Atomi
 #[allow(dead_code)]
 async fn setup_backend2<F> (obj: F) -> BootstrapResult<(Sender<()>, Arc<AtomicUsize>)>
     where F: BackendResponse + Send + 'static + Clone + Sync {
+    serve_backend(([127, 0, 0, 1], 17001).into(), obj).await
+}
+
+// HTTP middleware, kubeware posts JSON messages to it
+#[allow(dead_code)]
+async fn setup_webhook<F> (obj: F) -> BootstrapResult<(Sender<()>, Arc<AtomicUsize>)>
+    where F: BackendResponse + Send + 'static + Clone + Sync {
+    serve_backend(([127, 0, 0, 1], 17002).into(), obj).await
+}
+
+async fn serve_backend<F> (address: std::net::SocketAddr, obj: F) -> BootstrapResult<(Sender<()>, Arc<AtomicUsize>)>
+    where F: BackendResponse + Send + 'static + Clone + Sync {
 
-    let address = ([127, 0, 0, 1], 17001).into();
     let counter = Arc::new(AtomicUsize::new(0));
     let cloned_counter = Arc::clone(&counter);
 
@@ -204,8 +430,59 @@ async fn setup_backend2<F> (obj: F) -> BootstrapResult<(Sender<()>, Arc<AtomicUs
 
 #[allow(dead_code)]
 async fn setup_middleware (request: RequestFn, response: ResponseFn) -> BootstrapResult<(Sender<()>, Arc<AtomicUsize>, Arc<AtomicUsize>)> {
+    serve_middleware("127.0.0.1:17002", request, response, None, None).await
+}
+
+#[allow(dead_code)]
+async fn setup_error_middleware (request: RequestFn, response: ResponseFn, error: ErrorFn) -> BootstrapResult<(Sender<()>, Arc<AtomicUsize>, Arc<AtomicUsize>)> {
+    serve_middleware("127.0.0.1:17002", request, response, Some(error), None).await
+}
+
+#[allow(dead_code)]
+async fn setup_described_middleware (request: RequestFn, response: ResponseFn, description: DescribeResponse) -> BootstrapResult<(Sender<()>, Arc<AtomicUsize>, Arc<AtomicUsize>)> {
+    serve_middleware("127.0.0.1:17002", request, response, None, Some(description)).await
+}
+
+// V1 handlers are never called on a v2 middleware, so they answer with defaults
+#[allow(dead_code)]
+async fn setup_v2_middleware (request: RequestV2Fn, response: ResponseV2Fn) -> BootstrapResult<(Sender<()>, Arc<AtomicUsize>, Arc<AtomicUsize>)> {
+    let service = MiddlewareService::new(
+        Box::new(|_req| TonicResponse::new(RequestResponse::default())),
+        Box::new(|_req| TonicResponse::new(ResponseResponse::default())))
+        .with_v2(Some(request), Some(response));
 
-    let service = MiddlewareService::new(request, response);
+    serve("127.0.0.1:17002", service).await
+}
+
+#[allow(dead_code)]
+async fn setup_session_middleware (session: SessionFn) -> BootstrapResult<(Sender<()>, Arc<AtomicUsize>, Arc<AtomicUsize>, Arc<AtomicUsize>)> {
+    let service = MiddlewareService::new(
+        Box::new(|_req| TonicResponse::new(RequestResponse::default())),
+        Box::new(|_req| TonicResponse::new(ResponseResponse::default())))
+        .with_session(Some(session));
+    let session_counter = service.session_counter();
+
+    let (middleware_tx, request_counter, response_counter) = serve("127.0.0.1:17002", service).await?;
+
+    Ok((middleware_tx, request_counter, response_counter, session_counter))
+}
+
+// Middleware service doubles as a gRPC backend, kubeware proxies its calls from 17000 to 17001
+#[allow(dead_code)]
+async fn setup_grpc_backend (request: RequestFn, response: ResponseFn) -> BootstrapResult<(Sender<()>, Arc<AtomicUsize>, Arc<AtomicUsize>)> {
+    serve_middleware("127.0.0.1:17001", request, response, None, None).await
+}
+
+async fn serve_middleware (address: &str, request: RequestFn, response: ResponseFn, error: Option<ErrorFn>, description: Option<DescribeResponse>) -> BootstrapResult<(Sender<()>, Arc<AtomicUsize>, Arc<AtomicUsize>)> {
+
+    let service = MiddlewareService::new(request, response)
+        .with_error(error)
+        .with_description(description);
+
+    serve(address, service).await
+}
+
+async fn serve (address: &str, service: MiddlewareService) -> BootstrapResult<(Sender<()>, Arc<AtomicUsize>, Arc<AtomicUsize>)> {
     let request_counter = service.request_counter();
     let response_counter = service.response_counter();
 
@@ -213,7 +490,7 @@ async fn setup_middleware (request: RequestFn, response: ResponseFn) -> Bootstra
 
     let middleware = TonicServer::builder()
         .add_service(MiddlewareServer::new(service))
-        .serve_with_shutdown("127.0.0.1:17002".parse().unwrap(), async move {
+        .serve_with_shutdown(address.parse().unwrap(), async move {
             middleware_rx.await.ok();
         });
 
@@ -223,9 +500,124 @@ async fn setup_middleware (request: RequestFn, response: ResponseFn) -> Bootstra
         }
     });
 
+    wait_for(address).await;
+
     Ok((middleware_tx, request_counter, response_counter))
 }
 
+// Kubeware describes middlewares on connect, so they have to be listening before it starts
+async fn wait_for (address: &str) {
+    for _ in 0..50 {
+        match tokio::net::TcpStream::connect(address).await {
+            Ok(_) => break,
+            Err(_) => tokio::time::delay_for(Duration::from_millis(10)).await
+        }
+    }
+}
+
+pub struct ExtAuthzService {
+    check_fn: CheckFn,
+    counter: Arc<AtomicUsize>
+}
+
+#[tonic::async_trait]
+impl Authorization for ExtAuthzService {
+    async fn check(
+        &self,
+        request: TonicRequest<CheckRequest>,
+    ) -> Result<TonicResponse<CheckResponse>, Status> {
+        let _ = self.counter.fetch_add(1, Ordering::Relaxed);
+        Ok((self.check_fn)(request))
+    }
+}
+
+#[allow(dead_code)]
+async fn setup_ext_authz (check: CheckFn) -> BootstrapResult<(Sender<()>, Arc<AtomicUsize>)> {
+    let address = "127.0.0.1:17002";
+    let counter = Arc::new(AtomicUsize::new(0));
+    let service = ExtAuthzService { check_fn: check, counter: Arc::clone(&counter) };
+
+    let (middleware_tx, middleware_rx) = oneshot::channel::<()>();
+
+    let middleware = TonicServer::builder()
+        .add_service(AuthorizationServer::new(service))
+        .serve_with_shutdown(address.parse().unwrap(), async move {
+            middleware_rx.await.ok();
+        });
+
+    tokio::task::spawn(async move {
+        if let Err(e) = middleware.await {
+            error!("server error: {}", e);
+        }
+    });
+
+    wait_for(address).await;
+
+    Ok((middleware_tx, counter))
+}
+
+pub struct ExtProcService {
+    process_fn: ProcessFn,
+    stream_counter: Arc<AtomicUsize>,
+    message_counter: Arc<AtomicUsize>
+}
+
+#[tonic::async_trait]
+impl ExternalProcessor for ExtProcService {
+    type ProcessStream = mpsc::Receiver<Result<ProcessingResponse, Status>>;
+
+    async fn process(
+        &self,
+        request: TonicRequest<Streaming<ProcessingRequest>>,
+    ) -> Result<TonicResponse<Self::ProcessStream>, Status> {
+        let process_fn = Arc::clone(&self.process_fn);
+        let message_counter = Arc::clone(&self.message_counter);
+        let _ = self.stream_counter.fetch_add(1, Ordering::Relaxed);
+
+        let mut inbound = request.into_inner();
+        let (mut tx, rx) = mpsc::channel(4);
+
+        tokio::spawn(async move {
+            while let Ok(Some(message)) = inbound.message().await {
+                let _ = message_counter.fetch_add(1, Ordering::Relaxed);
+
+                if tx.send(Ok((process_fn)(&message))).await.is_err() {
+                    break
+                }
+            }
+        });
+
+        Ok(TonicResponse::new(rx))
+    }
+}
+
+// Counters are streams opened and messages received
+#[allow(dead_code)]
+async fn setup_ext_proc (process: ProcessFn) -> BootstrapResult<(Sender<()>, Arc<AtomicUsize>, Arc<AtomicUsize>)> {
+    let address = "127.0.0.1:17002";
+    let stream_counter = Arc::new(AtomicUsize::new(0));
+    let message_counter = Arc::new(AtomicUsize::new(0));
+    let service = ExtProcService { process_fn: process, stream_counter: Arc::clone(&stream_counter), message_counter: Arc::clone(&message_counter) };
+
+    let (middleware_tx, middleware_rx) = oneshot::channel::<()>();
+
+    let middleware = TonicServer::builder()
+        .add_service(ExternalProcessorServer::new(service))
+        .serve_with_shutdown(address.parse().unwrap(), async move {
+            middleware_rx.await.ok();
+        });
+
+    tokio::task::spawn(async move {
+        if let Err(e) = middleware.await {
+            error!("server error: {}", e);
+        }
+    });
+
+    wait_for(address).await;
+
+    Ok((middleware_tx, stream_counter, message_counter))
+}
+
 #[async_trait]
 trait BackendResponse {
     async fn handle(&mut self, request: Request<Body>) -> Response<Body>;
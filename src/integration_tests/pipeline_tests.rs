@@ -0,0 +1,291 @@
+#[cfg(test)]
+mod tests {
+    use tonic::{Request as TonicRequest, Response as TonicResponse};
+    use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse, ResponseStatus, Header};
+    use crate::integration_tests::{setup_middleware, setup_kubeware, setup_backend};
+    use hyper::{Body, Client, Request, Response};
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{Ordering};
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Same middleware declared three times, each entry only sees its own header, so calls can be told apart
+    const ORDER_CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [pipeline]
+        response_order = "reverse"
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = true
+        headers = ["x-c"]
+        phase = "late"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = true
+        headers = ["x-b"]
+        order = 1
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = true
+        headers = ["x-a"]
+    "#;
+
+    const STOP_CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [pipeline]
+        request_stop = "response_stage"
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = true
+    "#;
+
+    fn request() -> Request<Body> {
+        Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .header("x-a", "1")
+            .header("x-b", "1")
+            .header("x-c", "1")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn first_header(headers: &[Header]) -> String {
+        headers.first().map(|x| x.name.clone()).unwrap_or_default()
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_order_and_phase_set_request_stage_is_sorted_and_response_stage_is_reversed() -> Result<()> {
+        // Arrange
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let request_calls = Arc::clone(&calls);
+        let response_calls = Arc::clone(&calls);
+
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |req: TonicRequest<RequestRequest>| {
+                request_calls.lock().unwrap().push(["request", &first_header(&req.into_inner().headers)].join(" "));
+
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Success as i32,
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |req: TonicRequest<ResponseRequest>| {
+                response_calls.lock().unwrap().push(["response", &first_header(&req.into_inner().request_headers)].join(" "));
+
+                TonicResponse::new(ResponseResponse {
+                    status: ResponseStatus::Success as i32,
+                    ..Default::default()
+                })
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(ORDER_CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let res = Client::new().request(request()).await?;
+
+        // Assert
+        assert_eq!(200, res.status().as_u16());
+        assert_eq!(vec!["request x-a", "request x-b", "request x-c", "response x-c", "response x-b", "response x-a"], *calls.lock().unwrap());
+        assert_eq!(3, request_counter.load(Ordering::Relaxed));
+        assert_eq!(3, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_request_stage_stops_and_configured_response_stage_decorates_synthetic_response() -> Result<()> {
+        // Arrange
+        let response_bodies = Arc::new(Mutex::new(None));
+        let cloned_bodies = Arc::clone(&response_bodies);
+
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Stop as i32,
+                    body: Some("Denied".to_string()),
+                    status_code: Some(401),
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |req: TonicRequest<ResponseRequest>| {
+                let data = req.into_inner();
+                *cloned_bodies.lock().unwrap() = Some((data.request_body, data.response_body));
+
+                TonicResponse::new(ResponseResponse {
+                    status: ResponseStatus::Success as i32,
+                    added_headers: vec![
+                        Header {
+                            name: "access-control-allow-origin".to_string(),
+                            value: "*".to_string()
+                        }
+                    ],
+                    ..Default::default()
+                })
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(STOP_CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .method("POST")
+            .body(Body::from("Real body !"))
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        assert_eq!(401, parts.status.as_u16());
+        assert_eq!("*", parts.headers.get("access-control-allow-origin").unwrap());
+        assert_eq!("Denied", hyper::body::to_bytes(body).await?);
+        assert_eq!(Some(("Real body !".to_string(), "Denied".to_string())), *response_bodies.lock().unwrap());
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(1, response_counter.load(Ordering::Relaxed));
+        assert_eq!(0, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_stop_has_no_body_and_request_body_is_buffered_body_is_empty() -> Result<()> {
+        // Arrange
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Stop as i32,
+                    status_code: Some(403),
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(r#"
+            ip = "127.0.0.1"
+            port = 17000
+
+            [backend]
+            url = "http://127.0.0.1:17001"
+            version = "HTTP"
+
+            [[middleware]]
+            url = "http://127.0.0.1:17002"
+            request = true
+            response = true
+            request_body = true
+        "#).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .method("POST")
+            .body(Body::from("Real body !"))
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        assert_eq!(403, parts.status.as_u16());
+        assert_eq!("", hyper::body::to_bytes(body).await?);
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(0, response_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_stop_has_no_body_and_request_body_is_streamed_body_is_empty() -> Result<()> {
+        // Arrange
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Stop as i32,
+                    status_code: Some(403),
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(r#"
+            ip = "127.0.0.1"
+            port = 17000
+
+            [backend]
+            url = "http://127.0.0.1:17001"
+            version = "HTTP"
+
+            [[middleware]]
+            url = "http://127.0.0.1:17002"
+            request = true
+            response = true
+        "#).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .method("POST")
+            .body(Body::from("Real body !"))
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        assert_eq!(403, parts.status.as_u16());
+        assert_eq!("", hyper::body::to_bytes(body).await?);
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(0, response_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
     use tonic::{Request as TonicRequest, Response as TonicResponse};
-    use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse, ResponseStatus, Header};
+    use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse, ResponseStatus, Header, QueryParameter};
     use crate::integration_tests::{setup_middleware, setup_kubeware, setup_backend, BackendResponse, setup_backend2};
     use hyper::{Body, Client, Request, Response, HeaderMap};
     use std::sync::{Arc, Mutex};
@@ -25,6 +25,54 @@ mod tests {
         request = true
         response = false
     "#;
+    const RESPOND_CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = true
+    "#;
+    const SKIP_CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = false
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = false
+    "#;
+    const BACKENDS_CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17009"
+        version = "HTTP"
+
+        [backends.tenant]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = false
+    "#;
 
     #[tokio::test(core_threads = 5)]
     async fn when_sending_request_on_request_continue_full_flow_is_executed() -> Result<()> {
@@ -36,7 +84,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -45,7 +94,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
         let kubeware_tx = setup_kubeware(CONFIG).await?;
@@ -107,7 +157,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: Some(changed_body.to_string()),
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -116,7 +167,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -182,7 +234,8 @@ mod tests {
                     ],
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -191,7 +244,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -250,7 +304,8 @@ mod tests {
                     added_headers:  Vec::default(),
                     removed_headers: vec![HEADER_NAME.to_string(), HEADER2_NAME.to_string()],
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -259,7 +314,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -305,7 +361,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -314,7 +371,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
         let kubeware_tx = setup_kubeware(CONFIG).await?;
@@ -357,7 +415,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -366,7 +425,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
         let kubeware_tx = setup_kubeware(CONFIG).await?;
@@ -412,7 +472,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: Some(response_body.to_string()),
-                    status_code: Some(status_code)
+                    status_code: Some(status_code),
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -421,7 +482,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
         let kubeware_tx = setup_kubeware(CONFIG).await?;
@@ -473,7 +535,8 @@ mod tests {
                     ],
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -482,7 +545,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
         let kubeware_tx = setup_kubeware(CONFIG).await?;
@@ -545,7 +609,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: Some(changed_body.to_string()),
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -554,7 +619,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -620,7 +686,8 @@ mod tests {
                     ],
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -629,7 +696,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -688,7 +756,8 @@ mod tests {
                     added_headers:  Vec::default(),
                     removed_headers: vec![HEADER_NAME.to_string(), HEADER2_NAME.to_string()],
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -697,7 +766,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -730,6 +800,316 @@ mod tests {
         let _ = backend_tx.send(());
         let _ = middleware_tx.send(());
 
+        Ok(())
+    }
+    #[tokio::test(core_threads = 5)]
+    async fn when_sending_request_on_success_and_target_changed_request_is_rewritten_and_routed() -> Result<()> {
+        // Arrange
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Success as i32,
+                    method: Some("PUT".to_string()),
+                    path: Some("/v2/items".to_string()),
+                    backend: Some("tenant".to_string()),
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(BACKENDS_CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(move |req| {
+            Response::new(Body::from(format!("{} {}", req.method(), req.uri())))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/v1/items?page=2")
+            .method("POST")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        assert_eq!(200, parts.status.as_u16());
+        assert_eq!("PUT /v2/items?page=2", hyper::body::to_bytes(body).await?);
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(0, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_sending_request_on_success_and_backend_unknown_502_is_returned() -> Result<()> {
+        // Arrange
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Success as i32,
+                    query: Some(String::new()),
+                    backend: Some("missing".to_string()),
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(BACKENDS_CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(move |_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/?page=2")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(502, res.status().as_u16());
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(0, response_counter.load(Ordering::Relaxed));
+        assert_eq!(0, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+    #[tokio::test(core_threads = 5)]
+    async fn when_sending_request_on_success_and_query_params_changed_backend_receives_them() -> Result<()> {
+        // Arrange
+        let middleware_url = Arc::new(Mutex::new(None));
+        let cloned_url = Arc::clone(&middleware_url);
+
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |req: TonicRequest<RequestRequest>| {
+                let data = req.into_inner();
+                let params = data.query_params.into_iter().map(|x| (x.name, x.value)).collect::<Vec<(String, String)>>();
+                *cloned_url.lock().unwrap() = Some((data.scheme, data.host, data.path, params));
+
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Success as i32,
+                    removed_query_params: vec!["utm_source".to_string()],
+                    added_query_params: vec![
+                        QueryParameter {
+                            name: "api_key".to_string(),
+                            value: "s3cr3t&=".to_string()
+                        }
+                    ],
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(move |req| {
+            Response::new(Body::from(req.uri().to_string()))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/search?q=rust+lang&utm_source=mail&q=two")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        let expected_params = vec![
+            ("q".to_string(), "rust lang".to_string()),
+            ("utm_source".to_string(), "mail".to_string()),
+            ("q".to_string(), "two".to_string())
+        ];
+
+        assert_eq!(200, parts.status.as_u16());
+        assert_eq!("/search?q=rust+lang&q=two&api_key=s3cr3t%26%3D", hyper::body::to_bytes(body).await?);
+        assert_eq!(Some(("http".to_string(), "127.0.0.1".to_string(), "/search".to_string(), expected_params)), *middleware_url.lock().unwrap());
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(0, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+    #[tokio::test(core_threads = 5)]
+    async fn when_host_header_is_ipv6_literal_middleware_receives_it_without_port() -> Result<()> {
+        // Arrange
+        let middleware_host = Arc::new(Mutex::new(None));
+        let cloned_host = Arc::clone(&middleware_host);
+
+        let (middleware_tx, request_counter, _) = setup_middleware(
+            Box::new(move |req: TonicRequest<RequestRequest>| {
+                *cloned_host.lock().unwrap() = Some(req.into_inner().host);
+
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Stop as i32,
+                    status_code: Some(200),
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .header("host", "[::1]:8080")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(200, res.status().as_u16());
+        assert_eq!(Some("[::1]".to_string()), *middleware_host.lock().unwrap());
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+    #[tokio::test(core_threads = 5)]
+    async fn when_sending_request_on_request_respond_backend_is_skipped_and_response_stage_is_executed() -> Result<()> {
+        // Arrange
+        let response_body = Arc::new(Mutex::new(None));
+        let cloned_body = Arc::clone(&response_body);
+
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::Respond as i32,
+                    added_headers: vec![
+                        Header {
+                            name: HEADER_NAME.to_string(),
+                            value: "HIT".to_string()
+                        }
+                    ],
+                    body: Some("Cached body".to_string()),
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |req: TonicRequest<ResponseRequest>| {
+                *cloned_body.lock().unwrap() = Some(req.into_inner().response_body);
+
+                TonicResponse::new(ResponseResponse {
+                    status: ResponseStatus::Success as i32,
+                    added_headers: vec![
+                        Header {
+                            name: HEADER2_NAME.to_string(),
+                            value: 1.to_string()
+                        }
+                    ],
+                    ..Default::default()
+                })
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(RESPOND_CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(move |_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        assert_eq!(200, parts.status.as_u16());
+        assert_eq!("HIT", parts.headers.get(HEADER_NAME).unwrap());
+        assert!(parts.headers.contains_key(HEADER2_NAME));
+        assert_eq!("Cached body", hyper::body::to_bytes(body).await?);
+        assert_eq!(Some("Cached body".to_string()), *response_body.lock().unwrap());
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(1, response_counter.load(Ordering::Relaxed));
+        assert_eq!(0, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_sending_request_on_request_skip_stage_remaining_request_middlewares_are_skipped() -> Result<()> {
+        // Arrange
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse {
+                    status: ResponseStatus::SkipStage as i32,
+                    added_headers: vec![
+                        Header {
+                            name: HEADER_NAME.to_string(),
+                            value: 1.to_string()
+                        }
+                    ],
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse::default())
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(SKIP_CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(move |req| {
+            assert!(req.headers().contains_key(HEADER_NAME));
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        assert_eq!(200, parts.status.as_u16());
+        assert_eq!("OK", hyper::body::to_bytes(body).await?);
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(0, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
         Ok(())
     }
 }
\ No newline at end of file
@@ -23,6 +23,24 @@ mod tests {
         request = false
         response = true
     "#;
+    const SKIP_CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = false
+        response = true
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = false
+        response = true
+    "#;
 
     #[tokio::test(core_threads = 5)]
     async fn when_sending_response_on_response_continue_full_flow_is_executed() -> Result<()> {
@@ -34,7 +52,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -43,7 +62,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
         let kubeware_tx = setup_kubeware(CONFIG).await?;
@@ -89,7 +109,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -98,7 +119,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: Some(changed_body.to_string()),
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -141,7 +163,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -159,7 +182,8 @@ mod tests {
                     ],
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -203,7 +227,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -212,7 +237,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: vec![HEADER_NAME.to_string(), HEADER2_NAME.to_string()],
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -260,7 +286,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -269,7 +296,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
         let kubeware_tx = setup_kubeware(CONFIG).await?;
@@ -312,7 +340,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -321,7 +350,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
         let kubeware_tx = setup_kubeware(CONFIG).await?;
@@ -367,7 +397,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -376,7 +407,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: Some(response_body.to_string()),
-                    status_code: Some(status_code)
+                    status_code: Some(status_code),
+                    ..Default::default()
                 })
             })).await?;
         let kubeware_tx = setup_kubeware(CONFIG).await?;
@@ -419,7 +451,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -437,7 +470,8 @@ mod tests {
                     ],
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -484,7 +518,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -493,7 +528,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: Some(changed_body.to_string()),
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -535,7 +571,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -553,7 +590,8 @@ mod tests {
                     ],
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -596,7 +634,8 @@ mod tests {
                     added_headers:  Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -605,7 +644,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: vec![HEADER_NAME.to_string(), HEADER2_NAME.to_string()],
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -644,4 +684,98 @@ mod tests {
 
         Ok(())
     }
-}
\ No newline at end of file
+    #[tokio::test(core_threads = 5)]
+    async fn when_sending_response_on_response_skip_stage_remaining_response_middlewares_are_skipped() -> Result<()> {
+        // Arrange
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse::default())
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse {
+                    status: ResponseStatus::SkipStage as i32,
+                    body: Some("Changed body".to_string()),
+                    ..Default::default()
+                })
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(SKIP_CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(move |_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        assert_eq!(200, parts.status.as_u16());
+        assert_eq!("Changed body", hyper::body::to_bytes(body).await?);
+        assert_eq!(0, request_counter.load(Ordering::Relaxed));
+        assert_eq!(1, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_headers_are_repeated_all_values_are_forwarded() -> Result<()> {
+        // Arrange
+        let (middleware_tx, _, response_counter) = setup_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponse::default())
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponse {
+                    status: ResponseStatus::Success as i32,
+                    ..Default::default()
+                })
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|req| {
+            assert_eq!(2, req.headers().get_all(HEADER_NAME).iter().count());
+
+            Response::builder()
+                .header("set-cookie", "a=1")
+                .header("set-cookie", "b=2")
+                .body(Body::from("OK"))
+                .unwrap()
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .header(HEADER_NAME, "1")
+            .header(HEADER_NAME, "2")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        let cookies = res.headers().get_all("set-cookie").iter().map(|x| x.to_str().unwrap()).collect::<Vec<&str>>();
+
+        assert_eq!(200, res.status().as_u16());
+        assert_eq!(vec!["a=1", "b=2"], cookies);
+        assert_eq!(1, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+}
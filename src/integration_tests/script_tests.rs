@@ -0,0 +1,193 @@
+#[cfg(test)]
+mod tests {
+    use crate::integration_tests::{setup_kubeware, setup_backend};
+    use crate::config::Config;
+    use crate::middlewares::Middlewares;
+    use hyper::{Body, Client, Request, Response};
+    use std::sync::atomic::{Ordering};
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn config(path: &str) -> String {
+        format!(r#"
+            ip = "127.0.0.1"
+            port = 17000
+
+            [backend]
+            url = "http://127.0.0.1:17001"
+            version = "HTTP"
+
+            [[middleware]]
+            url = "{}"
+            protocol = "script"
+            timeout_ms = 100
+        "#, path)
+    }
+
+    // Writes the script into the temp dir, returns its path
+    fn script(name: &str, source: &str) -> Result<String> {
+        let path = std::env::temp_dir().join(format!("kubeware_{}.rhai", name));
+        std::fs::write(&path, source)?;
+
+        Ok(path.to_string_lossy().to_string())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_script_defines_both_stages_it_checks_and_rewrites_messages() -> Result<()> {
+        // Arrange
+        let path = script("token", r#"
+            fn request(req) {
+                let token = "";
+
+                for header in req.headers {
+                    if header.name == "x-token" { token = header.value; }
+                }
+
+                if token == "" {
+                    return #{ status: "STOP", statusCode: 401, body: "Missing token" };
+                }
+
+                #{ status: "SUCCESS", addedHeaders: [#{ name: "x-user", value: token }] }
+            }
+
+            fn response(res) {
+                #{ status: "SUCCESS", body: res.responseBody + " from script" }
+            }
+        "#)?;
+
+        let kubeware_tx = setup_kubeware(&config(&path)).await?;
+        let (backend_tx, backend_counter) = setup_backend(|req| {
+            assert_eq!("alice", req.headers().get("x-user").unwrap());
+
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let allowed = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .header("x-token", "alice")
+            .body(Body::empty())
+            .unwrap();
+        let denied = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap();
+
+        let allowed = Client::new().request(allowed).await?;
+        let denied = Client::new().request(denied).await?;
+
+        // Assert
+        assert_eq!(200, allowed.status().as_u16());
+        assert_eq!("OK from script", hyper::body::to_bytes(allowed.into_body()).await?);
+        assert_eq!(401, denied.status().as_u16());
+        assert_eq!("Missing token", hyper::body::to_bytes(denied.into_body()).await?);
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_script_never_returns_503_is_returned() -> Result<()> {
+        // Arrange
+        let path = script("loop", r#"
+            fn request(req) {
+                loop { }
+            }
+        "#)?;
+
+        let kubeware_tx = setup_kubeware(&config(&path)).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(503, res.status().as_u16());
+        assert_eq!(0, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_script_outgrows_max_string_size_503_is_returned() -> Result<()> {
+        // Arrange
+        let path = script("grow", r#"
+            fn request(req) {
+                let value = "x";
+
+                loop { value += value; }
+            }
+        "#)?;
+
+        let kubeware_tx = setup_kubeware(&format!("{}max_string_size = 1024", config(&path))).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(503, res.status().as_u16());
+        assert_eq!(0, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn when_script_fails_to_compile_startup_fails() -> Result<()> {
+        // Arrange
+        let path = script("invalid", "fn request(req) {")?;
+
+        let config: Config = toml::from_str(&config(&path))?;
+        let mut middlewares = Middlewares::with_config(&config);
+
+        // Act
+        let result = middlewares.insert(&config.middlewares[0]).await;
+
+        // Assert
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn when_script_middleware_enables_grpc_only_options_startup_fails() -> Result<()> {
+        // Arrange
+        let path = script("v2", "fn request(req) { #{ status: \"CONTINUE\" } }")?;
+        let config: Config = toml::from_str(&format!("{}api_version = 2", config(&path)))?;
+        let mut middlewares = Middlewares::with_config(&config);
+
+        // Act
+        let result = middlewares.insert(&config.middlewares[0]).await;
+
+        // Assert
+        assert!(result.unwrap_err().to_string().contains("doesn't support api_version"));
+
+        Ok(())
+    }
+}
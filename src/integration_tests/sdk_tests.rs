@@ -0,0 +1,194 @@
+#[cfg(test)]
+mod tests {
+    use crate::integration_tests::{setup_kubeware, setup_backend, wait_for};
+    use kubeware_sdk::{async_trait, Handler, Description, Server, Request as MiddlewareRequest, RequestReply, Response as MiddlewareResponse, ResponseReply};
+    use kubeware_sdk::health::HealthCheckRequest;
+    use kubeware_sdk::health::health_check_response::ServingStatus;
+    use kubeware_sdk::health::health_client::HealthClient;
+    use futures::channel::oneshot;
+    use hyper::{Body, Client, Request, Response};
+    use std::sync::atomic::{Ordering};
+    use std::time::Duration;
+    use tonic::Code;
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+    "#;
+
+    struct Token;
+
+    #[async_trait]
+    impl Handler for Token {
+        async fn request(&self, request: MiddlewareRequest) -> kubeware_sdk::Result<RequestReply> {
+            match request.header("X-Token") {
+                Some(val) => Ok(RequestReply::success().header("x-user", val).remove_header("x-token")),
+                None => Ok(RequestReply::stop(401).body("Missing token"))
+            }
+        }
+
+        async fn response(&self, response: MiddlewareResponse) -> kubeware_sdk::Result<ResponseReply> {
+            let user = response.request_header("x-user").unwrap_or_default().to_string();
+
+            match response.header("x-blocked") {
+                Some(_) => Ok(ResponseReply::stop(451).body("Blocked")),
+                None => Ok(ResponseReply::success().header("x-served-to", &user))
+            }
+        }
+    }
+
+    // Starts the SDK server on the middleware port, the returned sender triggers its shutdown
+    async fn setup_sdk_middleware(pre_stop_delay: Duration) -> oneshot::Sender<()> {
+        let (tx, rx) = oneshot::channel::<()>();
+
+        tokio::spawn(Server::new(Token)
+            .description(Description::new("token").request(true).response(true))
+            .shutdown(async move { rx.await.ok(); })
+            .pre_stop_delay(pre_stop_delay)
+            .serve("127.0.0.1:17002"));
+
+        wait_for("127.0.0.1:17002").await;
+
+        tx
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_middleware_is_written_with_sdk_replies_are_applied() -> Result<()> {
+        // Arrange
+        let middleware_tx = setup_sdk_middleware(Duration::from_millis(0)).await;
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|req| {
+            assert_eq!("alice", req.headers().get("x-user").unwrap());
+            assert!(req.headers().get("x-token").is_none());
+
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let authorized = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .header("x-token", "alice")
+            .body(Body::empty())
+            .unwrap();
+
+        let anonymous = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap();
+
+        let authorized_res = Client::new().request(authorized).await?;
+        let anonymous_res = Client::new().request(anonymous).await?;
+
+        // Assert
+        assert_eq!(200, authorized_res.status().as_u16());
+        assert_eq!("alice", authorized_res.headers().get("x-served-to").unwrap());
+        assert_eq!(401, anonymous_res.status().as_u16());
+        assert_eq!("Missing token", hyper::body::to_bytes(anonymous_res.into_body()).await?);
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_sdk_server_shuts_down_health_turns_not_serving() -> Result<()> {
+        // Arrange
+        let middleware_tx = setup_sdk_middleware(Duration::from_millis(300)).await;
+        let mut client = HealthClient::connect("http://127.0.0.1:17002").await?;
+
+        let before = client.check(HealthCheckRequest::default()).await?.into_inner();
+
+        // Act
+        let _ = middleware_tx.send(());
+        tokio::time::delay_for(Duration::from_millis(100)).await;
+
+        let after = client.check(HealthCheckRequest::default()).await?.into_inner();
+
+        // Assert
+        assert_eq!(ServingStatus::Serving as i32, before.status);
+        assert_eq!(ServingStatus::NotServing as i32, after.status);
+
+        // Cleanup, the port is released once the pre-stop delay is over
+        tokio::time::delay_for(Duration::from_millis(300)).await;
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_response_stage_stops_sdk_status_code_is_returned() -> Result<()> {
+        // Arrange
+        let middleware_tx = setup_sdk_middleware(Duration::from_millis(0)).await;
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, _backend_counter) = setup_backend(|_req| {
+            Response::builder()
+                .header("x-blocked", "1")
+                .body(Body::from("OK"))
+                .unwrap()
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .header("x-token", "alice")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(451, res.status().as_u16());
+        assert_eq!("Blocked", hyper::body::to_bytes(res.into_body()).await?);
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_health_is_checked_for_unknown_service_not_found_is_returned() -> Result<()> {
+        // Arrange
+        let middleware_tx = setup_sdk_middleware(Duration::from_millis(0)).await;
+        let mut client = HealthClient::connect("http://127.0.0.1:17002").await?;
+
+        // Act
+        let middleware = client.check(HealthCheckRequest { service: "kubeware.Middleware".to_string() }).await?.into_inner();
+        let unknown = client.check(HealthCheckRequest { service: "unknown.Service".to_string() }).await.unwrap_err();
+
+        // Assert
+        assert_eq!(ServingStatus::Serving as i32, middleware.status);
+        assert_eq!(Code::NotFound, unknown.code());
+
+        // Cleanup
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[test]
+    fn when_sdk_proto_is_vendored_it_matches_kubeware_proto() {
+        // Arrange
+        let kubeware = include_str!("../../proto/service.proto");
+
+        // Act
+        let sdk = include_str!("../../sdk/proto/service.proto");
+
+        // Assert
+        assert_eq!(kubeware, sdk);
+    }
+}
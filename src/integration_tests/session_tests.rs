@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod tests {
+    use crate::kubeware::{RequestResponse, ResponseResponse, ResponseStatus, SessionEvent, SessionDecision, Header};
+    use crate::kubeware::session_event::Event;
+    use crate::kubeware::session_decision::Decision;
+    use crate::integration_tests::{setup_session_middleware, setup_kubeware, setup_backend};
+    use hyper::{Body, Client, Request, Response};
+    use std::sync::Arc;
+    use std::sync::atomic::{Ordering};
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = true
+        session = true
+    "#;
+
+    // Body of the latest request or response event, collected from its chunks
+    fn latest_body(events: &[SessionEvent]) -> String {
+        let start = events.iter().rposition(|x| !matches!(x.event, Some(Event::Chunk(_)))).unwrap();
+        let bytes = events[start..].iter().filter_map(|x| match &x.event {
+            Some(Event::Chunk(val)) => Some(val.data.clone()),
+            _ => None
+        }).flatten().collect::<Vec<u8>>();
+
+        String::from_utf8(bytes).unwrap()
+    }
+
+    fn decide(events: &[SessionEvent]) -> SessionDecision {
+        let path = match &events[0].event {
+            Some(Event::Request(val)) => val.path.clone(),
+            _ => String::new()
+        };
+
+        let decision = match events.iter().rev().find(|x| !matches!(x.event, Some(Event::Chunk(_)))).and_then(|x| x.event.as_ref()) {
+            Some(Event::Request(_)) => Decision::Request(RequestResponse {
+                status: ResponseStatus::Success as i32,
+                added_headers: vec![Header { name: "x-session-body".to_string(), value: latest_body(events) }],
+                ..Default::default()
+            }),
+            _ => Decision::Response(ResponseResponse {
+                status: ResponseStatus::Success as i32,
+                body: Some(format!("{} answered {}", path, latest_body(events))),
+                ..Default::default()
+            })
+        };
+
+        SessionDecision { decision: Some(decision) }
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_middleware_uses_session_both_stages_share_one_stream_per_request() -> Result<()> {
+        // Arrange
+        let (middleware_tx, request_counter, response_counter, session_counter) = setup_session_middleware(Arc::new(decide)).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|req| {
+            let body = req.headers().get("x-session-body").unwrap().to_str().unwrap().to_string();
+            Response::new(Body::from(body))
+        }).await?;
+
+        // Act
+        let first = Request::builder()
+            .uri("http://127.0.0.1:17000/first")
+            .method("POST")
+            .body(Body::from("Real body !"))
+            .unwrap();
+        let second = Request::builder()
+            .uri("http://127.0.0.1:17000/second")
+            .method("POST")
+            .body(Body::from("Other body"))
+            .unwrap();
+
+        let first = Client::new().request(first).await?;
+        let second = Client::new().request(second).await?;
+
+        // Assert
+        assert_eq!(200, first.status().as_u16());
+        assert_eq!("/first answered Real body !", hyper::body::to_bytes(first.into_body()).await?);
+        assert_eq!("/second answered Other body", hyper::body::to_bytes(second.into_body()).await?);
+        assert_eq!(2, session_counter.load(Ordering::Relaxed));
+        assert_eq!(2, request_counter.load(Ordering::Relaxed));
+        assert_eq!(2, response_counter.load(Ordering::Relaxed));
+        assert_eq!(2, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use crate::integration_tests::{setup_kubeware, setup_backend};
+    use hyper::{Body, Client, Request, Response};
+    use std::sync::atomic::{Ordering};
+    use std::time::Duration;
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+        readiness_path = "/ready"
+
+        [shutdown]
+        pre_stop_delay_ms = 200
+        drain_timeout_ms = 1000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+    "#;
+
+    fn request(path: &str) -> Request<Body> {
+        Request::builder()
+            .uri(["http://127.0.0.1:17000", path].join(""))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_shutting_down_readiness_fails_and_requests_are_served_until_pre_stop_delay_elapses() -> Result<()> {
+        // Arrange
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let ready_before = Client::new().request(request("/ready")).await?;
+        let _ = kubeware_tx.send(());
+        tokio::time::delay_for(Duration::from_millis(50)).await;
+
+        let ready_during = Client::new().request(request("/ready")).await?;
+        let served_during = Client::new().request(request("/")).await?;
+        tokio::time::delay_for(Duration::from_millis(300)).await;
+
+        let refused_after = Client::new().request(request("/")).await;
+
+        // Assert
+        assert_eq!(200, ready_before.status().as_u16());
+        assert_eq!(503, ready_during.status().as_u16());
+        assert_eq!(200, served_during.status().as_u16());
+        assert!(refused_after.is_err());
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = backend_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_shutting_down_in_flight_requests_are_drained() -> Result<()> {
+        // Arrange
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            std::thread::sleep(Duration::from_millis(400));
+            Response::new(Body::from("Slow OK"))
+        }).await?;
+
+        // Act
+        let in_flight = tokio::spawn(Client::new().request(request("/")));
+        tokio::time::delay_for(Duration::from_millis(50)).await;
+        let _ = kubeware_tx.send(());
+
+        let res = in_flight.await??;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        assert_eq!(200, parts.status.as_u16());
+        assert_eq!("Slow OK", hyper::body::to_bytes(body).await?);
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = backend_tx.send(());
+
+        Ok(())
+    }
+}
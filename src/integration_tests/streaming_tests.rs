@@ -0,0 +1,190 @@
+#[cfg(test)]
+mod tests {
+    use tonic::{Request as TonicRequest, Response as TonicResponse};
+    use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse, ResponseStatus};
+    use crate::integration_tests::{setup_middleware, setup_kubeware, setup_backend, setup_backend2, BackendResponse};
+    use hyper::{Body, Client, Request, Response};
+    use hyper::body::HttpBody;
+    use futures::channel::oneshot;
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{Ordering};
+    use std::time::Duration;
+    use async_trait::async_trait;
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = true
+        request_body = false
+        response_body = false
+    "#;
+
+    const RESPONSE_BODY_CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = true
+        request_body = false
+    "#;
+
+    fn request_response() -> TonicResponse<RequestResponse> {
+        TonicResponse::new(RequestResponse {
+            status: ResponseStatus::Success as i32,
+            added_headers: Vec::default(),
+            removed_headers: Vec::default(),
+            body: None,
+            status_code: None,
+            ..Default::default()
+        })
+    }
+
+    fn response_response() -> TonicResponse<ResponseResponse> {
+        TonicResponse::new(ResponseResponse {
+            status: ResponseStatus::Success as i32,
+            added_headers: Vec::default(),
+            removed_headers: Vec::default(),
+            body: None,
+            status_code: None,
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_no_middleware_needs_body_response_is_streamed_to_client() -> Result<()> {
+        // Arrange
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| request_response()),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| response_response())).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+
+        let (finish_tx, finish_rx) = oneshot::channel::<()>();
+        let finish_rx = Arc::new(Mutex::new(Some(finish_rx)));
+        let (backend_tx, backend_counter) = setup_backend(move |_req| {
+            let (mut sender, body) = Body::channel();
+            let finish_rx = finish_rx.lock().unwrap().take().unwrap();
+
+            tokio::spawn(async move {
+                let _ = sender.send_data("data: first\n\n".into()).await;
+                let _ = finish_rx.await;
+                let _ = sender.send_data("data: second\n\n".into()).await;
+            });
+
+            Response::new(body)
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, mut body) = res.into_parts();
+
+        // Backend is still holding the stream open, a buffered body would never arrive
+        let first = tokio::time::timeout(Duration::from_secs(1), body.data()).await?.unwrap()?;
+        let _ = finish_tx.send(());
+        let second = hyper::body::to_bytes(body).await?;
+
+        // Assert
+        assert_eq!(200, parts.status.as_u16());
+        assert_eq!("data: first\n\n", first);
+        assert_eq!("data: second\n\n", second);
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(1, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_no_middleware_needs_request_body_it_is_streamed_to_backend() -> Result<()> {
+        // Arrange
+        #[derive(Clone)]
+        pub struct Backend {
+            pub body: Arc<Mutex<String>>
+        }
+
+        #[async_trait]
+        impl BackendResponse for Backend {
+            async fn handle(&mut self, request: Request<Body>) -> Response<Body> {
+                let full_body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                *self.body.lock().unwrap() = std::str::from_utf8(full_body.as_ref()).unwrap().to_string();
+
+                Response::new(Body::from("Backend body"))
+            }
+        }
+
+        let original_body = "Real body !".repeat(10_000);
+        let request_body = Arc::new(Mutex::new(None));
+        let response_bodies = Arc::new(Mutex::new(None));
+        let cloned_request_body = Arc::clone(&request_body);
+        let cloned_response_bodies = Arc::clone(&response_bodies);
+
+        let (middleware_tx, request_counter, response_counter) = setup_middleware(
+            Box::new(move |req: TonicRequest<RequestRequest>| {
+                *cloned_request_body.lock().unwrap() = Some(req.into_inner().body);
+                request_response()
+            }),
+            Box::new(move |req: TonicRequest<ResponseRequest>| {
+                let data = req.into_inner();
+                *cloned_response_bodies.lock().unwrap() = Some((data.request_body, data.response_body));
+                response_response()
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(RESPONSE_BODY_CONFIG).await?;
+        let backend = Backend { body: Arc::new(Mutex::new(String::default())) };
+        let backend_body = Arc::clone(&backend.body);
+        let (backend_tx, backend_counter) = setup_backend2(backend).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .method("POST")
+            .body(Body::from(original_body.clone()))
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+
+        // Assert
+        assert_eq!(200, parts.status.as_u16());
+        assert_eq!("Backend body", hyper::body::to_bytes(body).await?);
+        assert_eq!(original_body, *backend_body.lock().unwrap());
+        assert_eq!(Some(String::new()), *request_body.lock().unwrap());
+        assert_eq!(Some((String::new(), "Backend body".to_string())), *response_bodies.lock().unwrap());
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(1, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+}
@@ -37,7 +37,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -46,7 +47,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
         
@@ -91,7 +93,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -101,7 +104,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -146,7 +150,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -155,7 +160,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -202,7 +208,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -211,7 +218,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -257,7 +265,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -304,7 +313,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             }),
             Box::new(move |_req: TonicRequest<ResponseRequest>| {
@@ -313,7 +323,8 @@ mod tests {
                     added_headers: Vec::default(),
                     removed_headers: Vec::default(),
                     body: None,
-                    status_code: None
+                    status_code: None,
+                    ..Default::default()
                 })
             })).await?;
 
@@ -0,0 +1,156 @@
+#[cfg(test)]
+mod tests {
+    use tonic::{Request as TonicRequest, Response as TonicResponse};
+    use crate::kubeware::{RequestRequest, RequestResponseV2, ResponseRequest, ResponseResponseV2, ResponseStatus, Mutation, Header, RenameHeader, QueryParameter};
+    use crate::kubeware::mutation::Mutation::*;
+    use crate::integration_tests::{setup_v2_middleware, setup_kubeware, setup_backend};
+    use hyper::{Body, Client, Request, Response};
+    use hyper::body::HttpBody;
+    use std::sync::atomic::{Ordering};
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002"
+        request = true
+        response = true
+        api_version = 2
+    "#;
+
+    fn header(name: &str, value: &str) -> Header {
+        Header { name: name.to_string(), value: value.to_string() }
+    }
+
+    fn mutation(mutation: crate::kubeware::mutation::Mutation) -> Mutation {
+        Mutation { mutation: Some(mutation) }
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_v2_middleware_returns_mutations_they_are_applied_in_order() -> Result<()> {
+        // Arrange
+        let (middleware_tx, request_counter, response_counter) = setup_v2_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponseV2 {
+                    status: ResponseStatus::Success as i32,
+                    mutations: vec![
+                        mutation(AppendHeader(header("x-user", "alice"))),
+                        mutation(AppendHeader(header("x-user", "bob"))),
+                        mutation(RenameHeader(RenameHeader { from: "x-user".to_string(), to: "x-users".to_string() })),
+                        mutation(SetPath("/v2/items".to_string())),
+                        mutation(AddQueryParam(QueryParameter { name: "page".to_string(), value: "2".to_string() }))
+                    ],
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponseV2 {
+                    status: ResponseStatus::Success as i32,
+                    mutations: vec![
+                        mutation(SetBody("Replaced".to_string())),
+                        mutation(AppendHeader(header("set-cookie", "a=1"))),
+                        mutation(AppendHeader(header("set-cookie", "b=2"))),
+                        mutation(SetHeader(header("x-backend", "hidden"))),
+                        mutation(RemoveHeader("x-backend".to_string())),
+                        mutation(SetStatus(201))
+                    ],
+                    ..Default::default()
+                })
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|req| {
+            let users = req.headers().get_all("x-users").iter().map(|x| x.to_str().unwrap()).collect::<Vec<&str>>();
+
+            assert_eq!(vec!["alice", "bob"], users);
+            assert!(req.headers().get("x-user").is_none());
+            assert_eq!("/v2/items?page=2", req.uri().to_string());
+
+            Response::builder()
+                .header("x-backend", "1")
+                .body(Body::from("OK"))
+                .unwrap()
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/items")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+        let (parts, body) = res.into_parts();
+        let cookies = parts.headers.get_all("set-cookie").iter().map(|x| x.to_str().unwrap().to_string()).collect::<Vec<String>>();
+
+        // Assert
+        assert_eq!(201, parts.status.as_u16());
+        assert_eq!(vec!["a=1", "b=2"], cookies);
+        assert!(parts.headers.get("x-backend").is_none());
+        assert_eq!("Replaced", hyper::body::to_bytes(body).await?);
+        assert_eq!(1, request_counter.load(Ordering::Relaxed));
+        assert_eq!(1, response_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_v2_middleware_sets_trailer_http2_client_receives_it() -> Result<()> {
+        // Arrange
+        let (middleware_tx, _, _) = setup_v2_middleware(
+            Box::new(move |_req: TonicRequest<RequestRequest>| {
+                TonicResponse::new(RequestResponseV2 {
+                    status: ResponseStatus::Stop as i32,
+                    mutations: vec![
+                        mutation(SetStatus(200)),
+                        mutation(SetBody("Done".to_string())),
+                        mutation(SetTrailer(header("x-checksum", "abc")))
+                    ],
+                    ..Default::default()
+                })
+            }),
+            Box::new(move |_req: TonicRequest<ResponseRequest>| {
+                TonicResponse::new(ResponseResponseV2::default())
+            })).await?;
+
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::builder().http2_only(true).build_http().request(req).await?;
+        let mut body = res.into_body();
+        let mut data = Vec::new();
+
+        while let Some(chunk) = body.data().await {
+            data.extend_from_slice(&chunk?);
+        }
+
+        let trailers = body.trailers().await?;
+
+        // Assert
+        assert_eq!(b"Done".to_vec(), data);
+        assert_eq!(Some("abc"), trailers.as_ref().and_then(|x| x.get("x-checksum")).map(|x| x.to_str().unwrap()));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = middleware_tx.send(());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+#[cfg(test)]
+mod tests {
+    use crate::kubeware::{RequestResponse, ResponseResponse, ResponseStatus, Header};
+    use crate::integration_tests::{setup_kubeware, setup_backend};
+    use crate::config::Config;
+    use crate::middlewares::Middlewares;
+    use hyper::{Body, Client, Request, Response};
+    use prost::Message;
+    use std::sync::atomic::{Ordering};
+    use std::time::Instant;
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn config(path: &str) -> String {
+        format!(r#"
+            ip = "127.0.0.1"
+            port = 17000
+
+            [backend]
+            url = "http://127.0.0.1:17001"
+            version = "HTTP"
+
+            [[middleware]]
+            url = "{}"
+            protocol = "wasm"
+            max_fuel = 100000
+        "#, path)
+    }
+
+    // Compiles the module into the temp dir, returns its path
+    fn plugin(name: &str, source: &str) -> Result<String> {
+        let path = std::env::temp_dir().join(format!("kubeware_{}.wasm", name));
+        std::fs::write(&path, wat::parse_str(source)?)?;
+
+        Ok(path.to_string_lossy().to_string())
+    }
+
+    // Data segment string holding the encoded message
+    fn data<T: Message>(message: &T) -> Result<(String, usize)> {
+        let mut bytes = Vec::new();
+        message.encode(&mut bytes)?;
+
+        Ok((bytes.iter().map(|x| format!("\\{:02x}", x)).collect(), bytes.len()))
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_wasm_plugin_exports_hooks_both_stages_run_in_process() -> Result<()> {
+        // Arrange
+        let (request, request_len) = data(&RequestResponse {
+            status: ResponseStatus::Success as i32,
+            added_headers: vec![Header { name: "x-plugin".to_string(), value: "wasm".to_string() }],
+            ..Default::default()
+        })?;
+        let (response, response_len) = data(&ResponseResponse {
+            status: ResponseStatus::Success as i32,
+            body: Some("From wasm".to_string()),
+            ..Default::default()
+        })?;
+
+        let path = plugin("hooks", &format!(r#"
+            (module
+                (memory (export "memory") 1)
+                (data (i32.const 16) "{}")
+                (data (i32.const 4096) "{}")
+                (func (export "kubeware_alloc") (param i32) (result i32) (i32.const 8192))
+                (func (export "kubeware_request") (param i32 i32) (result i64) (i64.const {}))
+                (func (export "kubeware_response") (param i32 i32) (result i64) (i64.const {})))
+        "#, request, response, (16u64 << 32) | request_len as u64, (4096u64 << 32) | response_len as u64))?;
+
+        let kubeware_tx = setup_kubeware(&config(&path)).await?;
+        let (backend_tx, backend_counter) = setup_backend(|req| {
+            assert_eq!("wasm", req.headers().get("x-plugin").unwrap());
+
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(200, res.status().as_u16());
+        assert_eq!("From wasm", hyper::body::to_bytes(res.into_body()).await?);
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_wasm_plugin_runs_out_of_fuel_503_is_returned() -> Result<()> {
+        // Arrange
+        let path = plugin("loop", r#"
+            (module
+                (memory (export "memory") 1)
+                (func (export "kubeware_alloc") (param i32) (result i32) (i32.const 0))
+                (func (export "kubeware_request") (param i32 i32) (result i64)
+                    (loop $forever (br $forever))
+                    (i64.const 0)))
+        "#)?;
+
+        let kubeware_tx = setup_kubeware(&config(&path)).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(503, res.status().as_u16());
+        assert_eq!(0, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_wasm_call_exceeds_timeout_503_is_returned_without_waiting_for_fuel() -> Result<()> {
+        // Arrange
+        let path = plugin("slow", r#"
+            (module
+                (memory (export "memory") 1)
+                (func (export "kubeware_alloc") (param i32) (result i32) (i32.const 0))
+                (func (export "kubeware_request") (param i32 i32) (result i64)
+                    (loop $forever (br $forever))
+                    (i64.const 0)))
+        "#)?;
+
+        let kubeware_tx = setup_kubeware(&format!(r#"
+            ip = "127.0.0.1"
+            port = 17000
+
+            [backend]
+            url = "http://127.0.0.1:17001"
+            version = "HTTP"
+
+            [[middleware]]
+            url = "{}"
+            protocol = "wasm"
+            max_fuel = 200000000
+            timeout_ms = 100
+        "#, path)).await?;
+
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/")
+            .body(Body::empty())
+            .unwrap();
+
+        let timer = Instant::now();
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(503, res.status().as_u16());
+        assert!(timer.elapsed().as_millis() < 1000);
+        assert_eq!(0, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn when_wasm_module_fails_to_load_startup_fails() -> Result<()> {
+        // Arrange
+        let path = std::env::temp_dir().join("kubeware_invalid.wasm");
+        std::fs::write(&path, "not a wasm module")?;
+
+        let config: Config = toml::from_str(&config(&path.to_string_lossy()))?;
+        let mut middlewares = Middlewares::with_config(&config);
+
+        // Act
+        let result = middlewares.insert(&config.middlewares[0]).await;
+
+        // Assert
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn when_wasm_middleware_enables_grpc_only_options_startup_fails() -> Result<()> {
+        // Arrange
+        let path = plugin("empty", r#"(module (memory (export "memory") 1))"#)?;
+        let config: Config = toml::from_str(&format!("{}error = true", config(&path)))?;
+        let mut middlewares = Middlewares::with_config(&config);
+
+        // Act
+        let result = middlewares.insert(&config.middlewares[0]).await;
+
+        // Assert
+        assert!(result.unwrap_err().to_string().contains("doesn't support error"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+#[cfg(test)]
+mod tests {
+    use crate::kubeware::{RequestRequest, ResponseRequest};
+    use crate::integration_tests::{setup_webhook, setup_kubeware, setup_backend, BackendResponse};
+    use hyper::{Body, Client, Request, Response};
+    use std::sync::atomic::{Ordering};
+    use async_trait::async_trait;
+    use crate::config::Config;
+    use crate::middlewares::Middlewares;
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+    const CONFIG: &str = r#"
+        ip = "127.0.0.1"
+        port = 17000
+
+        [backend]
+        url = "http://127.0.0.1:17001"
+        version = "HTTP"
+
+        [[middleware]]
+        url = "http://127.0.0.1:17002/kubeware"
+        protocol = "http"
+        request = true
+        response = true
+    "#;
+
+    #[derive(Clone)]
+    pub struct Webhook;
+
+    // Written by hand, the way a middleware without protobuf would do it
+    #[async_trait]
+    impl BackendResponse for Webhook {
+        async fn handle(&mut self, request: Request<Body>) -> Response<Body> {
+            let path = request.uri().path().to_string();
+            let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+
+            let answer = match path.as_str() {
+                "/kubeware/request" => {
+                    let message: RequestRequest = serde_json::from_slice(&body).unwrap();
+                    format!(r#"{{"status": "SUCCESS", "addedHeaders": [{{"name": "x-path", "value": "{}"}}]}}"#, message.path)
+                },
+                _ => {
+                    let message: ResponseRequest = serde_json::from_slice(&body).unwrap();
+                    format!(r#"{{"status": 2, "statusCode": 418, "body": "Got {}"}}"#, message.response_body)
+                }
+            };
+
+            Response::new(Body::from(answer))
+        }
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_middleware_uses_http_protocol_json_messages_are_exchanged() -> Result<()> {
+        // Arrange
+        let (webhook_tx, webhook_counter) = setup_webhook(Webhook).await?;
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|req| {
+            assert_eq!("/items", req.headers().get("x-path").unwrap().to_str().unwrap());
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/items")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(418, res.status().as_u16());
+        assert_eq!("Got OK", hyper::body::to_bytes(res.into_body()).await?);
+        assert_eq!(2, webhook_counter.load(Ordering::Relaxed));
+        assert_eq!(1, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+        let _ = webhook_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test(core_threads = 5)]
+    async fn when_http_middleware_is_down_503_is_returned() -> Result<()> {
+        // Arrange
+        let kubeware_tx = setup_kubeware(CONFIG).await?;
+        let (backend_tx, backend_counter) = setup_backend(|_req| {
+            Response::new(Body::from("OK"))
+        }).await?;
+
+        // Act
+        let req = Request::builder()
+            .uri("http://127.0.0.1:17000/items")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = Client::new().request(req).await?;
+
+        // Assert
+        assert_eq!(503, res.status().as_u16());
+        assert_eq!(0, backend_counter.load(Ordering::Relaxed));
+
+        // Cleanup
+        let _ = kubeware_tx.send(());
+        let _ = backend_tx.send(());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn when_http_middleware_enables_grpc_only_options_startup_fails() -> Result<()> {
+        // Arrange
+        let config: Config = toml::from_str(r#"
+            [backend]
+            url = "http://127.0.0.1:17001"
+
+            [[middleware]]
+            url = "http://127.0.0.1:17002"
+            protocol = "http"
+            request = true
+            error = true
+        "#)?;
+
+        let mut middlewares = Middlewares::with_config(&config);
+
+        // Act
+        let result = middlewares.insert(&config.middlewares[0]).await;
+
+        // Assert
+        assert!(result.is_err());
+        assert!(middlewares.all().is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use hyper::{Request, Body, Response};
+use tower_layer::Layer;
+use tower_service::Service;
+use crate::config::{Config, Backend};
+use crate::middlewares::Middlewares;
+use crate::request_handler::RequestHandler;
+use crate::trailers::{self, TrailersBody};
+use crate::upstream::{Upstream, Inner};
+
+type GenericError = Box<dyn std::error::Error + Send + Sync>;
+
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+// Runs the middleware pipeline in front of any service, without the sidecar hop
+#[derive(Clone)]
+pub struct KubewareLayer {
+    middlewares: Arc<Shared>,
+    config: Config
+}
+
+impl KubewareLayer {
+    // Middlewares are expected to be inserted already, see Middlewares::with_config.
+    // [backend] can be left out, its url would be ignored anyway.
+    pub fn new(middlewares: Middlewares, mut config: Config) -> KubewareLayer {
+        config.backend.get_or_insert_with(Backend::default);
+        let shared = Shared { current: RwLock::new(Arc::new(middlewares)), attempted: Mutex::new(None) };
+
+        KubewareLayer { middlewares: Arc::new(shared), config }
+    }
+}
+
+impl<S> Layer<S> for KubewareLayer
+    where S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+          S::Future: Send,
+          S::Error: Into<GenericError> {
+    type Service = Kubeware;
+
+    fn layer(&self, service: S) -> Kubeware {
+        Kubeware {
+            middlewares: Arc::clone(&self.middlewares),
+            config: self.config.clone(),
+            upstream: Arc::new(Inner::new(service))
+        }
+    }
+}
+
+// Middlewares of every service made by the layer, swapped once unresolved ones are reconnected
+struct Shared {
+    current: RwLock<Arc<Middlewares>>,
+    attempted: Mutex<Option<Instant>>
+}
+
+impl Shared {
+    // The request hitting a due attempt waits for it, others go on with the current middlewares
+    async fn middlewares(&self) -> Arc<Middlewares> {
+        let current = Arc::clone(&self.current.read().unwrap_or_else(|x| x.into_inner()));
+
+        if current.all().iter().all(|x| x.resolved()) || !self.attempt_due() {
+            return current
+        }
+
+        debug!("Trying to reconnect to unreachable hosts...");
+
+        match current.ensure_connected().await {
+            Ok(val) => {
+                let val = Arc::new(val);
+                *self.current.write().unwrap_or_else(|x| x.into_inner()) = Arc::clone(&val);
+
+                val
+            },
+            Err(err) => {
+                error!("Failed to ensure middlewares are resolved. {}", err);
+
+                current
+            }
+        }
+    }
+
+    fn attempt_due(&self) -> bool {
+        let mut attempted = self.attempted.lock().unwrap_or_else(|x| x.into_inner());
+
+        match *attempted {
+            Some(val) if val.elapsed() < RECONNECT_INTERVAL => false,
+            _ => {
+                *attempted = Some(Instant::now());
+                true
+            }
+        }
+    }
+}
+
+// Errors of the wrapped service are handled like backend ones (error stage, 502), so it never fails itself
+#[derive(Clone)]
+pub struct Kubeware {
+    middlewares: Arc<Shared>,
+    config: Config,
+    upstream: Arc<dyn Upstream>
+}
+
+impl Service<Request<Body>> for Kubeware {
+    type Response = Response<TrailersBody>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    // Wrapped service readiness is awaited per request
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let shared = Arc::clone(&self.middlewares);
+        let config = self.config.clone();
+        let upstream = Arc::clone(&self.upstream);
+
+        Box::pin(async move {
+            let middlewares = shared.middlewares().await;
+
+            match RequestHandler::handle(req, middlewares, config, upstream).await {
+                Ok(val) => Ok(trailers::attach(val)),
+                Err(err) => {
+                    error!("Failed to parse request: {:?}", err);
+
+                    Ok(trailers::attach(RequestHandler::generic_error()))
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,63 @@
+// Pipeline as a library, so Rust services can run kubeware middlewares in-process through KubewareLayer
+pub mod config;
+pub mod middlewares;
+pub mod middleware;
+pub mod handler;
+pub mod registry;
+pub mod builtins;
+pub mod container_handler;
+pub mod request_handler;
+pub mod upstream;
+pub mod layer;
+pub mod tower_service;
+pub mod listener;
+pub mod shutdown;
+mod request_container;
+mod body_chunks;
+mod grpc;
+mod ext_authz;
+mod ext_proc;
+mod limits;
+mod query;
+mod session;
+mod webhook;
+mod wasm;
+mod script;
+mod trailers;
+#[cfg(test)]
+mod integration_tests;
+
+#[macro_use]
+extern crate log;
+
+pub use crate::config::Config;
+pub use crate::middlewares::Middlewares;
+pub use crate::layer::{KubewareLayer, Kubeware};
+pub use crate::trailers::TrailersBody;
+// Shared with the SDK so middlewares and kubeware can't disagree on it
+pub use kubeware_sdk::PROTOCOL_VERSION;
+
+pub const LOOPBACK: &str = "127.0.0.1";
+pub const PORT: u16 = 17_000;
+pub const DEFAULT_TIMEOUT_MILLIS: u32 = 5_000;
+pub const KUBEWARE_TIME_HEADER: &str = "x-kubeware-time";
+pub const BACKEND_TIME_HEADER: &str  = "x-backend-time";
+pub const RUST_LOG: &str = "RUST_LOG";
+pub const DEFAULT_LOGGING_LEVEL: &str = "INFO";
+
+// Oneofs mixing messages and body chunks are generated as enums of very different sizes
+#[allow(clippy::large_enum_variant)]
+pub mod kubeware {
+    tonic::include_proto!("kubeware");
+}
+
+// Envoy ext_authz, see proto/envoy/ext_authz.proto
+pub mod envoy_auth {
+    tonic::include_proto!("envoy.service.auth.v3");
+}
+
+// Envoy ext_proc, see proto/envoy/ext_proc.proto
+#[allow(clippy::large_enum_variant)]
+pub mod envoy_ext_proc {
+    tonic::include_proto!("envoy.service.ext_proc.v3");
+}
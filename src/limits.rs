@@ -0,0 +1,133 @@
+use hyper::{Body, HeaderMap};
+use hyper::body::HttpBody;
+use hyper::header::CONTENT_LENGTH;
+use bytes::{Bytes, BytesMut};
+use futures::StreamExt;
+use std::fmt;
+use crate::config::Config;
+
+type GenericError = Box<dyn std::error::Error + Send + Sync>;
+type Result<T> = std::result::Result<T, GenericError>;
+
+#[derive(Debug)]
+pub struct BodyTooLarge {
+    limit: u64
+}
+
+impl fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Body exceeds the limit of {} bytes", self.limit)
+    }
+}
+
+impl std::error::Error for BodyTooLarge {}
+
+pub struct BodyLimits {
+    pub request: Option<u64>,
+    pub response: Option<u64>
+}
+
+impl BodyLimits {
+    // Route with the longest matching path prefix wins, missing values fall back to the global ones
+    pub fn for_path(config: &Config, path: &str) -> BodyLimits {
+        let route = config.routes.iter()
+            .filter(|x| path.starts_with(x.path.as_str()))
+            .max_by_key(|x| x.path.len());
+
+        match route {
+            Some(val) => BodyLimits {
+                request: val.max_request_body_bytes.or(config.max_request_body_bytes),
+                response: val.max_response_body_bytes.or(config.max_response_body_bytes)
+            },
+            None => BodyLimits {
+                request: config.max_request_body_bytes,
+                response: config.max_response_body_bytes
+            }
+        }
+    }
+}
+
+// Streamed bodies fail inside the backend call, so the limit error can be any of the sources
+pub fn is_too_large(err: &GenericError) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(err.as_ref());
+
+    while let Some(val) = source {
+        if val.is::<BodyTooLarge>() {
+            return true
+        }
+
+        source = val.source();
+    }
+
+    false
+}
+
+// Content-Length lets us reject before reading anything, chunked bodies are checked while reading
+pub fn exceeds(headers: &HeaderMap, limit: Option<u64>) -> bool {
+    let length = headers.get(CONTENT_LENGTH)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.parse::<u64>().ok());
+
+    match (length, limit) {
+        (Some(length), Some(limit)) => length > limit,
+        _ => false
+    }
+}
+
+pub async fn read_body(mut body: Body, limit: Option<u64>) -> Result<Bytes> {
+    let limit = match limit {
+        Some(val) => val,
+        None => return Ok(hyper::body::to_bytes(body).await?)
+    };
+
+    let mut buffer = BytesMut::new();
+
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+
+        if (buffer.len() + chunk.len()) as u64 > limit {
+            return Err(Box::new(BodyTooLarge { limit }))
+        }
+
+        buffer.extend_from_slice(&chunk);
+    }
+
+    Ok(buffer.freeze())
+}
+
+// Streamed body is aborted as soon as it goes over the limit. Requests are still answered with 413,
+// responses are cut off as their status code is already sent by then.
+pub fn limit_stream(body: Body, limit: Option<u64>) -> Body {
+    let limit = match limit {
+        Some(val) => val,
+        None => return body
+    };
+
+    let mut read: u64 = 0;
+
+    Body::wrap_stream(body.map(move |chunk| {
+        let chunk = chunk?;
+        read += chunk.len() as u64;
+
+        match read > limit {
+            true => Err(Box::new(BodyTooLarge { limit }) as GenericError),
+            false => Ok(chunk)
+        }
+    }))
+}
+
+// Cuts the body sent to middlewares on a char boundary, returns whether anything was cut
+pub fn truncate(body: String, limit: Option<u64>) -> (String, bool) {
+    let limit = match limit {
+        Some(val) if (val as usize) < body.len() => val as usize,
+        _ => return (body, false)
+    };
+
+    let mut index = limit;
+
+    while !body.is_char_boundary(index) {
+        index -= 1;
+    }
+
+    (body[..index].to_string(), true)
+}
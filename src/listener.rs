@@ -0,0 +1,33 @@
+use hyper::Server;
+use hyper::server::Builder;
+use hyper::server::conn::AddrIncoming;
+use std::net::SocketAddr;
+use std::time::Duration;
+use crate::config::{Listener, HttpVersion};
+
+// Without listener config both HTTP/1 and HTTP/2 prior knowledge (h2c) are accepted on the same port
+pub fn bind(address: &SocketAddr, listener: &Option<Listener>) -> Builder<AddrIncoming> {
+    let server = Server::bind(address);
+
+    let listener = match listener {
+        Some(val) => val,
+        None => return server
+    };
+
+    let server = match &listener.version {
+        Some(HttpVersion::HTTP) => server.http1_only(true),
+        Some(HttpVersion::HTTP2) => server.http2_only(true),
+        None => server
+    };
+
+    let server = server
+        .http2_max_concurrent_streams(listener.http2_max_concurrent_streams)
+        .http2_initial_stream_window_size(listener.http2_initial_stream_window_size)
+        .http2_initial_connection_window_size(listener.http2_initial_connection_window_size)
+        .http2_keep_alive_interval(listener.http2_keep_alive_interval_ms.map(|x| Duration::from_millis(x as u64)));
+
+    match listener.http2_keep_alive_timeout_ms {
+        Some(val) => server.http2_keep_alive_timeout(Duration::from_millis(val as u64)),
+        None => server
+    }
+}
@@ -1,44 +1,24 @@
-mod request_container;
-mod middlewares;
-mod config;
-mod tower_service;
-mod request_handler;
-mod middleware;
-mod container_handler;
-mod integration_tests;
-
 extern crate pretty_env_logger;
 #[macro_use]
 extern crate log;
 
-use crate::config::{Config, HttpVersion};
+use kubeware::{Config, Middlewares, listener, shutdown};
+use kubeware::{LOOPBACK, PORT, RUST_LOG, DEFAULT_LOGGING_LEVEL};
+use kubeware::tower_service::Builder;
+use kubeware::shutdown::Shutdown;
+use kubeware::upstream::Clients;
 use std::fs::{File};
 use std::env::{var, set_var};
 use std::path::{Path};
 use std::io::Read;
-use hyper::{Client, Server};
 use std::net::ToSocketAddrs;
 use std::sync::{Arc};
 use std::sync::Mutex;
-
-use middlewares::{Middlewares};
-use crate::tower_service::Builder;
+use futures::future::{self, Either};
 
 type GenericError = Box<dyn std::error::Error + Send + Sync>;
 type Result<T> = std::result::Result<T, GenericError>;
 
-const LOOPBACK: &str = "127.0.0.1";
-const PORT: u16 = 17_000;
-const DEFAULT_TIMEOUT_MILLIS: u32 = 5_000;
-const KUBEWARE_TIME_HEADER: &str = "x-kubeware-time";
-const BACKEND_TIME_HEADER: &str  = "x-backend-time";
-const RUST_LOG: &str = "RUST_LOG";
-const DEFAULT_LOGGING_LEVEL: &str = "INFO";
-
-pub mod kubeware {
-    tonic::include_proto!("kubeware");
-}
-
 #[tokio::main]
 async fn main() -> Result<()> {
 
@@ -51,6 +31,11 @@ async fn main() -> Result<()> {
     config_file?.read_to_string(&mut config_content)?;
 
     let config: Config = toml::from_str(config_content.as_str())?;
+
+    if config.backend.is_none() {
+        return Err("Config needs a [backend]".into())
+    }
+
     let address = [config.ip.clone().unwrap_or(LOOPBACK.to_string()), config.port.clone().unwrap_or(PORT).to_string()]
         .join(":")
         .to_socket_addrs()?
@@ -73,31 +58,24 @@ async fn main() -> Result<()> {
         middlewares.insert(middleware).await?;
     }
 
-    let http_client = match &config.backend.version {
-        Some(version) => match version {
-            HttpVersion::HTTP => Client::new(),
-            HttpVersion::HTTP2 => Client::builder().http2_only(true).build_http()
-        },
-        _ => Client::new()
-    };
+    let shutdown = Shutdown::with_config(&config);
 
-    let bind_server = Server::bind(&address).serve(Builder {
-        http_client,
+    let bind_server = listener::bind(&address, &config.listener).serve(Builder {
+        upstream: Arc::new(Clients::new()),
         config,
         mutex: Mutex::new(false),
+        ready: shutdown.ready(),
         middlewares: Arc::new(middlewares)
     });
 
-    let server = bind_server.with_graceful_shutdown(sigterm_signal());
+    let (stop_accepting, drain_deadline) = shutdown.start(shutdown::sigterm_signal());
+    let server = bind_server.with_graceful_shutdown(stop_accepting);
 
-    if let Err(err) = server.await {
-        error!("Fatal error: {:?}", err);
-    }
+    match future::select(Box::pin(server), Box::pin(drain_deadline)).await {
+        Either::Left((Err(err), _)) => error!("Fatal error: {:?}", err),
+        Either::Left((Ok(_), _)) => info!("All connections drained."),
+        Either::Right(_) => warn!("Drain timeout elapsed, closing remaining connections.")
+    };
 
     Ok(())
 }
-
-async fn sigterm_signal() {
-    tokio::signal::ctrl_c().await
-        .expect("failed to install SIGTERM handler");
-}
\ No newline at end of file
@@ -1,36 +1,79 @@
-use tonic::transport::Channel;
-use crate::kubeware::middleware_client::MiddlewareClient;
 use std::time::Duration;
+use std::sync::Arc;
+use crate::handler::MiddlewareHandler;
 use crate::DEFAULT_TIMEOUT_MILLIS;
+use crate::config::Phase;
 
 #[derive(Clone)]
 pub struct Middleware {
+    id: usize,
     url: String,
-    connection: Option<MiddlewareClient<Channel>>,
+    handler: Option<Arc<dyn MiddlewareHandler>>,
     timeout: Duration,
     request: bool,
-    response: bool
+    response: bool,
+    request_body: bool,
+    response_body: bool,
+    headers: Option<Vec<String>>,
+    error: bool,
+    api_version: u32,
+    session: bool,
+    stream_body: bool,
+    order: i32,
+    phase: Phase
 }
 
 pub struct MiddlewareBuilder {
+    id: usize,
     url: Option<String>,
-    connection: Option<MiddlewareClient<Channel>>,
+    handler: Option<Arc<dyn MiddlewareHandler>>,
     request: Option<bool>,
     response: Option<bool>,
+    request_body: Option<bool>,
+    response_body: Option<bool>,
+    headers: Option<Vec<String>>,
+    error: Option<bool>,
+    api_version: Option<u32>,
+    session: Option<bool>,
+    stream_body: Option<bool>,
+    order: Option<i32>,
+    phase: Option<Phase>,
     timeout_millis: Option<u32>
 }
 
+impl Default for MiddlewareBuilder {
+    fn default() -> MiddlewareBuilder {
+        MiddlewareBuilder::new()
+    }
+}
+
 impl MiddlewareBuilder {
     pub fn new() -> MiddlewareBuilder {
         MiddlewareBuilder {
+            id: 0,
             url: None,
-            connection: None,
+            handler: None,
             request: None,
             response: None,
+            request_body: None,
+            response_body: None,
+            headers: None,
+            error: None,
+            api_version: None,
+            session: None,
+            stream_body: None,
+            order: None,
+            phase: None,
             timeout_millis: None
         }
     }
 
+    // Position in the declaration, identifies the middleware within a request
+    pub fn id(mut self, id: usize) -> MiddlewareBuilder {
+        self.id = id;
+        self
+    }
+
     pub fn url(mut self, url: String) -> MiddlewareBuilder {
         self.url = Some(url);
         self
@@ -46,8 +89,55 @@ impl MiddlewareBuilder {
         self
     }
 
-    pub fn connection(mut self, connection: Option<MiddlewareClient<Channel>>) -> MiddlewareBuilder {
-        self.connection = connection;
+    pub fn request_body(mut self, enabled: Option<bool>) -> MiddlewareBuilder {
+        self.request_body = enabled;
+        self
+    }
+
+    pub fn response_body(mut self, enabled: Option<bool>) -> MiddlewareBuilder {
+        self.response_body = enabled;
+        self
+    }
+
+    // Header names are matched case-insensitively
+    pub fn headers(mut self, headers: Option<Vec<String>>) -> MiddlewareBuilder {
+        self.headers = headers.map(|x| x.iter().map(|name| name.to_lowercase()).collect());
+        self
+    }
+
+    pub fn error(mut self, enabled: Option<bool>) -> MiddlewareBuilder {
+        self.error = enabled;
+        self
+    }
+
+    pub fn api_version(mut self, version: Option<u32>) -> MiddlewareBuilder {
+        self.api_version = version;
+        self
+    }
+
+    pub fn session(mut self, enabled: Option<bool>) -> MiddlewareBuilder {
+        self.session = enabled;
+        self
+    }
+
+    pub fn stream_body(mut self, enabled: Option<bool>) -> MiddlewareBuilder {
+        self.stream_body = enabled;
+        self
+    }
+
+    pub fn order(mut self, order: Option<i32>) -> MiddlewareBuilder {
+        self.order = order;
+        self
+    }
+
+    pub fn phase(mut self, phase: Option<Phase>) -> MiddlewareBuilder {
+        self.phase = phase;
+        self
+    }
+
+    // None while the middleware can't be reached, it is retried later
+    pub fn handler(mut self, handler: Option<Arc<dyn MiddlewareHandler>>) -> MiddlewareBuilder {
+        self.handler = handler;
         self
     }
 
@@ -62,10 +152,20 @@ impl MiddlewareBuilder {
 
     pub fn build(&self) -> Middleware {
         Middleware {
+            id: self.id,
             url: self.url.as_ref().unwrap().to_string(),
-            connection: self.connection.to_owned(),
+            handler: self.handler.to_owned(),
             request: self.request.unwrap_or(false),
             response: self.response.unwrap_or(false),
+            request_body: self.request_body.unwrap_or(true),
+            response_body: self.response_body.unwrap_or(true),
+            headers: self.headers.clone(),
+            error: self.error.unwrap_or(false),
+            api_version: self.api_version.unwrap_or(1),
+            session: self.session.unwrap_or(false),
+            stream_body: self.stream_body.unwrap_or(false),
+            order: self.order.unwrap_or(0),
+            phase: self.phase.unwrap_or(Phase::Normal),
             timeout:  Duration::from_millis(self.timeout_millis.unwrap() as u64)
         }
     }
@@ -73,6 +173,8 @@ impl MiddlewareBuilder {
 
 
 impl Middleware {
+    pub fn id(&self) -> usize { self.id }
+
     pub fn url(&self) -> &String { &self.url }
 
     #[allow(dead_code)]
@@ -81,7 +183,30 @@ impl Middleware {
     #[allow(dead_code)]
     pub fn response(&self) -> bool { self.response }
 
-    pub fn connection(&self) -> &Option<MiddlewareClient<Channel>> { &self.connection }
+    pub fn error(&self) -> bool { self.error }
+
+    // V2 middlewares answer with an ordered list of mutations
+    pub fn v2(&self) -> bool { self.api_version >= 2 }
+
+    // Session middlewares get both stages over one stream per request
+    pub fn session(&self) -> bool { self.session }
+
+    // Bodies are sent in chunks after the metadata, without the middleware body limit
+    pub fn stream_body(&self) -> bool { self.stream_body }
+
+    pub fn request_body(&self) -> bool { self.request_body }
+
+    pub fn response_body(&self) -> bool { self.response_body }
+
+    // None means all headers are sent
+    pub fn headers(&self) -> &Option<Vec<String>> { &self.headers }
+
+    // Middlewares run by phase, then order, then declaration
+    pub fn position(&self) -> (Phase, i32) { (self.phase, self.order) }
+
+    pub fn handler(&self) -> &Option<Arc<dyn MiddlewareHandler>> { &self.handler }
+
+    pub fn resolved(&self) -> bool { self.handler.is_some() }
 
     pub fn timeout(&self) -> Duration { self.timeout }
 }
\ No newline at end of file
@@ -1,6 +1,19 @@
 use crate::kubeware::middleware_client::MiddlewareClient;
-use crate::config::{MiddlewareConfig, Config};
+use crate::envoy_auth::authorization_client::AuthorizationClient;
+use crate::envoy_ext_proc::external_processor_client::ExternalProcessorClient;
+use crate::config::{MiddlewareConfig, Config, ResponseOrder, Protocol};
 use crate::middleware::{Middleware, MiddlewareBuilder};
+use crate::kubeware::{DescribeRequest, DescribeResponse};
+use crate::{DEFAULT_TIMEOUT_MILLIS, PROTOCOL_VERSION};
+use tonic::transport::Channel;
+use tonic::Code;
+use std::time::Duration;
+use hyper::Client;
+use std::sync::Arc;
+use crate::wasm::{self, Plugin};
+use crate::script::{self, Script};
+use crate::handler;
+use crate::registry::Registry;
 
 type GenericError = Box<dyn std::error::Error + Send + Sync>;
 type Result<T> = std::result::Result<T, GenericError>;
@@ -8,7 +21,8 @@ type Result<T> = std::result::Result<T, GenericError>;
 #[derive(Clone)]
 pub struct Middlewares {
     inner: Vec<Middleware>,
-    config: Config
+    config: Config,
+    registry: Arc<Registry>
 }
 
 impl Middlewares {
@@ -16,34 +30,66 @@ impl Middlewares {
         &self.inner
     }
 
+    // Sort is stable, so middlewares with the same phase and order keep the declaration order
     pub fn request(&self) -> Vec<&Middleware> {
-        self.inner.iter().filter(|x| x.request() == true).collect()
+        let mut middlewares = self.inner.iter().filter(|x| x.request()).collect::<Vec<&Middleware>>();
+        middlewares.sort_by_key(|x| x.position());
+
+        middlewares
     }
 
+    pub fn error(&self) -> Vec<&Middleware> {
+        let mut middlewares = self.inner.iter().filter(|x| x.error()).collect::<Vec<&Middleware>>();
+        middlewares.sort_by_key(|x| x.position());
+
+        middlewares
+    }
+
+    // With reverse order the response stage unwinds the request stage (onion model)
     pub fn response(&self) -> Vec<&Middleware> {
-        self.inner.iter().filter(|x| x.response() == true).collect()
+        let mut middlewares = self.inner.iter().filter(|x| x.response()).collect::<Vec<&Middleware>>();
+        middlewares.sort_by_key(|x| x.position());
+
+        match self.config.pipeline.as_ref().and_then(|x| x.response_order.as_ref()) {
+            Some(ResponseOrder::Reverse) => middlewares.into_iter().rev().collect(),
+            _ => middlewares
+        }
+    }
+
+    // Request body is sent in both stages, so it has to be buffered if any enabled middleware needs it
+    pub fn request_body_required(&self) -> bool {
+        self.inner.iter().any(|x| (x.request() || x.response() || x.error()) && x.request_body())
+    }
+
+    pub fn response_body_required(&self) -> bool {
+        self.inner.iter().any(|x| x.response() && x.response_body())
     }
 
     pub fn with_config(config: &Config) -> Middlewares {
+        Middlewares::with_registry(config, Registry::default())
+    }
+
+    // Registry holds the middlewares config entries can refer to by `type`
+    pub fn with_registry(config: &Config, registry: Registry) -> Middlewares {
         Middlewares {
             inner: Vec::default(),
-            config: config.clone()
+            config: config.clone(),
+            registry: Arc::new(registry)
         }
     }
 
     pub async fn ensure_connected(&self) -> Result<Middlewares> {
-        let mut middlewares = Middlewares::with_config(&self.config);
+        let mut middlewares = Middlewares { inner: Vec::default(), config: self.config.clone(), registry: Arc::clone(&self.registry) };
 
         for middleware in &self.inner {
-            match &middleware.connection() {
-                Some(_) => middlewares.insert_existing(&middleware),
-                None => {
+            match middleware.resolved() {
+                true => middlewares.insert_existing(middleware),
+                false => {
                     debug!("Trying to reconnect to {}", middleware.url());
 
-                    let config_item = self.config.middlewares.iter()
-                        .filter(|x| &x.url == middleware.url())
-                        .collect::<Vec<&MiddlewareConfig>>();
-                    let config_value = config_item.first().unwrap();
+                    // Ids are the config index, urls can be shared by several entries
+                    let config_value = self.config.middlewares.get(middleware.id())
+                        .ok_or_else(|| format!("Middleware [{}] has no config entry {}", middleware.url(), middleware.id()))?;
                     middlewares.insert(config_value).await?;
                 }
             }
@@ -56,29 +102,243 @@ impl Middlewares {
         self.inner.push(item.clone())
     }
 
+    // Describe is optional, middlewares which don't implement it answer with UNIMPLEMENTED
+    async fn describe(url: &str, connection: &mut MiddlewareClient<Channel>) -> Result<Option<DescribeResponse>> {
+        let request = DescribeRequest { protocol_version: PROTOCOL_VERSION };
+        let timeout = Duration::from_millis(DEFAULT_TIMEOUT_MILLIS as u64);
+
+        match tokio::time::timeout(timeout, connection.describe(request)).await {
+            Ok(Ok(response)) => {
+                let description = response.into_inner();
+
+                if description.protocol_version > PROTOCOL_VERSION {
+                    return Err(format!("Middleware [{}] requires protocol version {}, supported is {}", url, description.protocol_version, PROTOCOL_VERSION).into())
+                }
+
+                info!("Middleware [{}] described itself as {} (protocol version {})", url, description.name, description.protocol_version);
+
+                Ok(Some(description))
+            },
+            Ok(Err(status)) if status.code() == Code::Unimplemented => Ok(None),
+            Ok(Err(status)) => {
+                warn!("Error describing middleware [{}]: {}", url, status);
+
+                Ok(None)
+            },
+            Err(_err) => {
+                warn!("Timed out describing middleware [{}]", url);
+
+                Ok(None)
+            }
+        }
+    }
+
+    // Config wins, the description only fills in what was omitted
+    fn builder(middleware: &MiddlewareConfig, description: Option<&DescribeResponse>) -> MiddlewareBuilder {
+        match description {
+            Some(val) => {
+                if middleware.request == Some(true) && !val.request {
+                    warn!("Middleware [{}] does not support the request stage, but it is enabled.", middleware.url)
+                }
+
+                if middleware.response == Some(true) && !val.response {
+                    warn!("Middleware [{}] does not support the response stage, but it is enabled.", middleware.url)
+                }
+            },
+            // Built-in, Envoy and in-process protocols come with default stages, error-only middlewares need none
+            None => if middleware.request.is_none() && middleware.response.is_none() && middleware.error != Some(true) && middleware.kind.is_none()
+                && !matches!(middleware.protocol, Some(Protocol::ExtAuthz) | Some(Protocol::ExtProc) | Some(Protocol::Wasm) | Some(Protocol::Script)) {
+                warn!("Middleware [{}] has no stages configured or described, it stays disabled.", middleware.url)
+            }
+        };
+
+        MiddlewareBuilder::new()
+            .url(middleware.url.clone())
+            .request(middleware.request.or_else(|| description.map(|x| x.request)).unwrap_or(false))
+            .response(middleware.response.or_else(|| description.map(|x| x.response)).unwrap_or(false))
+            .request_body(middleware.request_body.or_else(|| description.map(|x| x.request_body)))
+            .response_body(middleware.response_body.or_else(|| description.map(|x| x.response_body)))
+            .headers(middleware.headers.clone().or_else(|| description.map(|x| x.headers.clone()).filter(|x| !x.is_empty())))
+            .error(middleware.error)
+            .api_version(middleware.api_version.or_else(|| description.map(|x| x.api_version).filter(|x| *x > 0)))
+            .session(middleware.session.or_else(|| description.map(|x| x.session)))
+            .stream_body(middleware.stream_body.or_else(|| description.map(|x| x.stream_body)))
+            .order(middleware.order)
+            .phase(middleware.phase)
+            .timeout_millis(middleware.timeout_ms.or_else(|| description.and_then(|x| x.timeout_ms)))
+    }
+
+    // Enabled options which only the gRPC protocol implements
+    fn grpc_only_options(middleware: &MiddlewareConfig) -> Vec<&'static str> {
+        let options = [
+            ("api_version", middleware.api_version.map(|x| x > 1)),
+            ("session", middleware.session),
+            ("stream_body", middleware.stream_body),
+            ("error", middleware.error)
+        ];
+
+        options.iter().filter(|x| x.1 == Some(true)).map(|x| x.0).collect()
+    }
+
+    // Other protocols fail the startup rather than ignoring these options, or failing every call for error
+    fn check_options(middleware: &MiddlewareConfig, name: &str, kind: &str, v2: bool) -> Result<()> {
+        let unsupported = Middlewares::grpc_only_options(middleware).into_iter()
+            .filter(|x| !(v2 && *x == "api_version"))
+            .collect::<Vec<&str>>();
+
+        match unsupported.is_empty() {
+            true => Ok(()),
+            false => Err(format!("Middleware [{}] {}, which doesn't support {}", name, kind, unsupported.join(", ")).into())
+        }
+    }
+
     pub async fn insert(&mut self, middleware: &MiddlewareConfig) -> Result<()> {
+        let id = self.inner.len();
+
+        // Built-in middlewares run in-process, only the body defaults differ as they rarely need it
+        if let Some(kind) = &middleware.kind {
+            let factory = self.registry.get(kind).ok_or_else(|| format!("Unknown middleware type [{}]", kind))?;
+            let url = match middleware.url.is_empty() { true => kind.clone(), false => middleware.url.clone() };
+            Middlewares::check_options(middleware, &url, "is built-in", false)?;
+
+            self.inner.push(Middlewares::builder(middleware, None)
+                .id(id)
+                .url(url)
+                .request(middleware.request.unwrap_or(true))
+                .response(middleware.response.unwrap_or(true))
+                .request_body(middleware.request_body.or(Some(false)))
+                .response_body(middleware.response_body.or(Some(false)))
+                .handler(Some(factory(middleware)?))
+                .build());
+
+            return Ok(())
+        }
+
+        if middleware.url.is_empty() {
+            return Err("Middleware needs either a url or a type".into())
+        }
+
+        // HTTP middlewares are not described, the config is all there is
+        if middleware.protocol == Some(Protocol::Http) {
+            Middlewares::check_options(middleware, &middleware.url, "speaks HTTP", false)?;
+
+            self.inner.push(Middlewares::builder(middleware, None)
+                .id(id)
+                .handler(Some(handler::shared(Client::new())))
+                .build());
+
+            return Ok(())
+        }
+
+        // ext_authz only covers the request stage
+        if middleware.protocol == Some(Protocol::ExtAuthz) {
+            Middlewares::check_options(middleware, &middleware.url, "speaks ext_authz", false)?;
+
+            if middleware.response == Some(true) {
+                warn!("Middleware [{}] speaks ext_authz, which has no response stage, but it is enabled.", middleware.url)
+            }
+
+            let connection = AuthorizationClient::connect(middleware.url.clone()).await
+                .map_err(|err| warn!("Error connecting to middleware [{}]: {}", middleware.url, err))
+                .ok();
+
+            self.inner.push(Middlewares::builder(middleware, None)
+                .id(id)
+                .request(middleware.request.unwrap_or(true))
+                .response(false)
+                .handler(connection.map(handler::shared))
+                .build());
+
+            return Ok(())
+        }
+
+        // Wasm modules are loaded from disk, the stages default to the exported hooks
+        if middleware.protocol == Some(Protocol::Wasm) {
+            Middlewares::check_options(middleware, &middleware.url, "speaks wasm", true)?;
+
+            let plugin = Plugin::load(&middleware.url, middleware.max_fuel, middleware.max_memory_bytes)
+                .map_err(|err| format!("Error loading wasm middleware [{}]: {}", middleware.url, err))?;
+
+            let request = plugin.exports(wasm::REQUEST_EXPORT);
+            let response = plugin.exports(wasm::RESPONSE_EXPORT);
+
+            self.inner.push(Middlewares::builder(middleware, None)
+                .id(id)
+                .request(middleware.request.unwrap_or(request))
+                .response(middleware.response.unwrap_or(response))
+                .handler(Some(handler::shared(plugin)))
+                .build());
+
+            return Ok(())
+        }
+
+        // Scripts are like wasm modules, the stages default to the defined functions
+        if middleware.protocol == Some(Protocol::Script) {
+            Middlewares::check_options(middleware, &middleware.url, "speaks script", false)?;
+
+            let script = Script::load(middleware)
+                .map_err(|err| format!("Error loading script middleware [{}]: {}", middleware.url, err))?;
+
+            let request = script.defines(script::REQUEST_FN);
+            let response = script.defines(script::RESPONSE_FN);
+
+            self.inner.push(Middlewares::builder(middleware, None)
+                .id(id)
+                .request(middleware.request.unwrap_or(request))
+                .response(middleware.response.unwrap_or(response))
+                .handler(Some(handler::shared(script)))
+                .build());
+
+            return Ok(())
+        }
+
+        // ext_proc follows Envoy's default processing mode, headers of both stages and no bodies
+        if middleware.protocol == Some(Protocol::ExtProc) {
+            Middlewares::check_options(middleware, &middleware.url, "speaks ext_proc", false)?;
+
+            let connection = ExternalProcessorClient::connect(middleware.url.clone()).await
+                .map_err(|err| warn!("Error connecting to middleware [{}]: {}", middleware.url, err))
+                .ok();
+
+            self.inner.push(Middlewares::builder(middleware, None)
+                .id(id)
+                .request(middleware.request.unwrap_or(true))
+                .response(middleware.response.unwrap_or(true))
+                .request_body(middleware.request_body.or(Some(false)))
+                .response_body(middleware.response_body.or(Some(false)))
+                .handler(connection.map(handler::shared))
+                .build());
+
+            return Ok(())
+        }
+
         let connection = MiddlewareClient::connect(middleware.url.clone()).await;
 
-        self.inner.push(match connection {
-            Ok(val) => MiddlewareBuilder::new()
-                .url(middleware.url.clone())
-                .connection(Some(val))
-                .request(middleware.request)
-                .response(middleware.response)
-                .timeout_millis(middleware.timeout_ms)
-                .build(),
+        let item = match connection {
+            Ok(mut val) => {
+                let description = Middlewares::describe(&middleware.url, &mut val).await?;
+
+                Middlewares::builder(middleware, description.as_ref())
+                    .id(id)
+                    .handler(Some(handler::shared(val)))
+                    .build()
+            },
             Err(err) => {
                 warn!("Error connecting to middleware [{}]: {}", middleware.url, err);
 
-                MiddlewareBuilder::new()
-                    .url(middleware.url.clone())
-                    .connection(None)
-                    .request(middleware.request)
-                    .response(middleware.response)
-                    .timeout_millis(middleware.timeout_ms)
+                Middlewares::builder(middleware, None)
+                    .id(id)
+                    .handler(None)
                     .build()
             }
-        });
+        };
+
+        // Streamed bodies only have v1 RPCs outside of the session stream, the combination would be silently ignored
+        if item.stream_body() && (item.session() || item.v2()) {
+            return Err(format!("Middleware [{}] can't combine stream_body with session or api_version 2", middleware.url).into())
+        }
+
+        self.inner.push(item);
 
         Ok(())
     }
@@ -0,0 +1,25 @@
+use crate::kubeware::QueryParameter;
+
+pub fn parse(query: Option<&str>) -> Vec<QueryParameter> {
+    match query {
+        Some(val) => form_urlencoded::parse(val.as_bytes())
+            .map(|(name, value)| QueryParameter { name: name.into_owned(), value: value.into_owned() })
+            .collect(),
+        None => Vec::default()
+    }
+}
+
+// Removed names drop every occurrence, added parameters are appended (repeated keys are allowed)
+pub fn edit(query: Option<String>, removed: &[String], added: &[QueryParameter]) -> Option<String> {
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+
+    for param in parse(query.as_deref()).iter().filter(|x| !removed.contains(&x.name)) {
+        serializer.append_pair(&param.name, &param.value);
+    }
+
+    for param in added {
+        serializer.append_pair(&param.name, &param.value);
+    }
+
+    Some(serializer.finish()).filter(|x| !x.is_empty())
+}
@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::config::MiddlewareConfig;
+use crate::handler::MiddlewareHandler;
+use crate::builtins::{self, Headers, RequestId};
+
+type GenericError = Box<dyn std::error::Error + Send + Sync>;
+type Result<T> = std::result::Result<T, GenericError>;
+
+// Builds the middleware from its config entry, errors fail the startup
+pub type Factory = Box<dyn Fn(&MiddlewareConfig) -> Result<Arc<dyn MiddlewareHandler>> + Send + Sync>;
+
+// In-process middlewares by their `type`, they run in the same pipeline as the remote ones
+pub struct Registry {
+    inner: HashMap<String, Factory>
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry { inner: HashMap::new() }
+    }
+
+    pub fn register<F>(mut self, name: &str, factory: F) -> Registry
+        where F: Fn(&MiddlewareConfig) -> Result<Arc<dyn MiddlewareHandler>> + Send + Sync + 'static {
+        self.inner.insert(name.to_string(), Box::new(factory));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Factory> {
+        self.inner.get(name)
+    }
+}
+
+// Built-in middlewares
+impl Default for Registry {
+    fn default() -> Registry {
+        Registry::new()
+            .register(builtins::HEADERS, Headers::from_config)
+            .register(builtins::REQUEST_ID, RequestId::from_config)
+    }
+}
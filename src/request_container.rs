@@ -1,7 +1,8 @@
 use hyper::{Uri, Method, Version, HeaderMap};
+use hyper::http::uri::Authority;
 use bytes::Bytes;
 use crate::kubeware::{Header};
-use hyper::header::{HeaderName, HeaderValue};
+use hyper::header::{HeaderName, HeaderValue, HOST};
 use crate::request_container::ContainerState::MiddlewareRequest;
 use std::str::from_utf8;
 
@@ -100,6 +101,10 @@ impl RequestContainer {
 
     pub fn status_code_set(&mut self, status_code: u16) { self.status_code = Some(status_code) }
 
+    pub fn method_set(&mut self, method: Method) { self.method = method }
+
+    pub fn uri_set(&mut self, uri: Uri) { self.uri = uri }
+
     pub fn add_request_headers(&mut self, headers:&Vec<Header>) -> Result<()> {
         for header in headers {
             self.request.headers.insert(HeaderName::from_lowercase(header.name.to_lowercase().as_bytes())?,
@@ -118,6 +123,44 @@ impl RequestContainer {
         Ok(())
     }
 
+    fn headers_mut(&mut self) -> &mut HeaderMap {
+        match self.state {
+            MiddlewareRequest => &mut self.request.headers,
+            _ => &mut self.response.headers
+        }
+    }
+
+    pub fn set_header(&mut self, header: &Header) -> Result<()> {
+        let name = HeaderName::from_lowercase(header.name.to_lowercase().as_bytes())?;
+        self.headers_mut().insert(name, HeaderValue::from_str(&header.value)?);
+
+        Ok(())
+    }
+
+    pub fn append_header(&mut self, header: &Header) -> Result<()> {
+        let name = HeaderName::from_lowercase(header.name.to_lowercase().as_bytes())?;
+        self.headers_mut().append(name, HeaderValue::from_str(&header.value)?);
+
+        Ok(())
+    }
+
+    pub fn remove_header(&mut self, name: &str) {
+        self.headers_mut().remove(name);
+    }
+
+    // All values are moved, so renaming keeps repeated headers
+    pub fn rename_header(&mut self, from: &str, to: &str) -> Result<()> {
+        let name = HeaderName::from_lowercase(to.to_lowercase().as_bytes())?;
+        let values = self.headers_mut().get_all(from).iter().cloned().collect::<Vec<HeaderValue>>();
+        self.headers_mut().remove(from);
+
+        for value in values {
+            self.headers_mut().append(name.clone(), value);
+        }
+
+        Ok(())
+    }
+
     pub fn body_set_string(&mut self, body: String) {
         match self.state {
             MiddlewareRequest => self.request.body = Bytes::from(body),
@@ -150,13 +193,26 @@ impl RequestContainer {
 
     pub fn uri (&self) -> String { self.uri.to_string() }
 
-    pub fn headers (&self) -> Vec<Header> {
-        match self.state {
-            MiddlewareRequest => self.request_headers(),
-            _ => self.response_headers()
+    pub fn path (&self) -> String { self.uri.path().to_string() }
+
+    pub fn query (&self) -> Option<String> { self.uri.query().map(|x| x.to_string()) }
+
+    pub fn scheme (&self) -> String { self.uri.scheme_str().unwrap_or("http").to_string() }
+
+    // HTTP/2 requests carry the authority in the uri, HTTP/1 ones in the Host header
+    pub fn host (&self) -> String {
+        match self.uri.host() {
+            Some(val) => val.to_string(),
+            None => self.request.headers.get(HOST)
+                .and_then(|x| x.to_str().ok())
+                .and_then(|x| x.parse::<Authority>().ok())
+                .map(|x| x.host().to_string())
+                .unwrap_or_default()
         }
     }
 
+    pub fn path_and_query (&self) -> String { self.uri.path_and_query().map(|x| x.as_str()).unwrap_or("/").to_string() }
+
     pub fn request_headers (&self) -> Vec<Header> {
         self.request.headers.iter().map(|x| Header {
             name: x.0.to_string(),
@@ -171,6 +227,10 @@ impl RequestContainer {
         }).collect::<Vec<Header>>()
     }
 
+    pub fn request_body_bytes (&self) -> Bytes { self.request.body.clone() }
+
+    pub fn response_body_bytes (&self) -> Bytes { self.response.body.clone() }
+
     pub fn request_body (&self) -> Result<String> { Ok(from_utf8(self.request.body.as_ref())?.to_string()) }
 
     pub fn response_body (&self) -> Result<String> { Ok(from_utf8(self.response.body.as_ref())?.to_string()) }
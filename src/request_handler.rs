@@ -1,40 +1,61 @@
 use std::sync::{Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use futures::future;
 use crate::middlewares::Middlewares;
-use hyper::{Client, Request, Body, Response};
-use hyper::client::HttpConnector;
-use crate::config::{Config};
+use hyper::{Request, Body, Response, Version};
+use crate::config::{Config, Backend, HttpVersion, RequestStop};
 use std::time::{Instant, Duration};
 use hyper::service::Service;
 use std::pin::Pin;
 use std::future::Future;
 use std::task::{Context, Poll};
-use crate::kubeware::{ResponseStatus};
+use crate::kubeware::{ResponseStatus, ErrorKind};
 use crate::container_handler::ContainerHandler;
 use crate::request_container::ContainerState::{MiddlewareResponse, Response as BackendResponse};
-use tonic::metadata::{MetadataValue};
 use crate::{DEFAULT_TIMEOUT_MILLIS, KUBEWARE_TIME_HEADER};
 use hyper::header::HeaderValue;
+use crate::grpc;
+use crate::trailers::{self, TrailersBody};
+use crate::limits::{self, BodyLimits};
+use tonic::Code;
+use crate::upstream::Upstream;
 
 type HandlerResult<T> = std::result::Result<T, GenericError>;
 type GenericError = Box<dyn std::error::Error + Send + Sync>;
-const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
 
 pub struct RequestHandler
 {
     pub middlewares: Arc<Middlewares>,
-    pub http_client: Client<HttpConnector>,
-    pub config: Config
+    pub upstream: Arc<dyn Upstream>,
+    pub config: Config,
+    pub ready: Arc<AtomicBool>
+}
+
+enum StageResult {
+    Completed,
+    Stopped,
+    Responded,
+    Unavailable
 }
 
 impl RequestHandler {
 
-    fn generic_error() -> Response<Body> {
+    pub fn generic_error() -> Response<Body> {
         let response = Response::builder();
         let body = Body::from(Vec::from(&b"Internal server error"[..]));
 
         response.status(500).body(body).unwrap()
     }
 
+    fn readiness(ready: bool) -> Response<Body> {
+        let response = Response::builder();
+
+        match ready {
+            true => response.status(200).body(Body::from(Vec::from(&b"OK"[..]))).unwrap(),
+            false => response.status(503).body(Body::from(Vec::from(&b"Shutting down"[..]))).unwrap()
+        }
+    }
+
     fn gateway_error(timer: Instant) -> HandlerResult<Response<Body>> {
         let response = Response::builder()
             .header(KUBEWARE_TIME_HEADER, HeaderValue::from_str(&timer.elapsed().as_millis().to_string())?);
@@ -51,6 +72,14 @@ impl RequestHandler {
         Ok(response.status(503).body(body).unwrap())
     }
 
+    fn payload_too_large(timer: Instant) -> HandlerResult<Response<Body>> {
+        let response = Response::builder()
+            .header(KUBEWARE_TIME_HEADER, HeaderValue::from_str(&timer.elapsed().as_millis().to_string())?);
+        let body = Body::from(Vec::from(&b"Payload Too Large"[..]));
+
+        Ok(response.status(413).body(body).unwrap())
+    }
+
     fn gateway_timeout(timer: Instant) -> HandlerResult<Response<Body>> {
         let response = Response::builder()
             .header(KUBEWARE_TIME_HEADER, HeaderValue::from_str(&timer.elapsed().as_millis().to_string())?);
@@ -59,113 +88,329 @@ impl RequestHandler {
         Ok(response.status(504).body(body).unwrap())
     }
 
-    async fn handle(req: Request<Body>, middlewares: Arc<Middlewares>, config: Config, http_client: Client<HttpConnector>) -> Result<Response<Body>, GenericError> {
-        let url = [config.backend.url.as_str(), req.uri().path()].join("");
-        let mut container = ContainerHandler::new(req, url).await?;
+    // Inbound and outbound protocols are independent, HTTP/2 (h2c) clients can still talk to HTTP/1 backends
+    fn backend_version(backend: &Backend, version: Version) -> Version {
+        match (&backend.version, version) {
+            (Some(HttpVersion::HTTP2), _) => Version::HTTP_2,
+            (_, Version::HTTP_2) => Version::HTTP_11,
+            (_, val) => val
+        }
+    }
+
+    fn backend_timeout(backend: &Backend) -> Duration {
+        Duration::from_millis(backend.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MILLIS) as u64)
+    }
+
+    // Middlewares can route the request to one of the named backends
+    fn resolve_backend(config: &Config, container: &ContainerHandler) -> Option<Backend> {
+        match config.backend_by_name(container.backend()) {
+            Some(val) => Some(val.clone()),
+            None => {
+                error!("[Backend] Unknown backend {}.", container.backend().unwrap_or_default());
+                None
+            }
+        }
+    }
+
+    // Whole pipeline for a single request, the upstream is either the configured backends or the service wrapped by the layer
+    pub async fn handle(req: Request<Body>, middlewares: Arc<Middlewares>, config: Config, upstream: Arc<dyn Upstream>) -> Result<Response<Body>, GenericError> {
+        if grpc::is_grpc(req.headers()) {
+            return RequestHandler::handle_grpc(req, middlewares, config, upstream).await
+        }
+
+        let limits = BodyLimits::for_path(&config, req.uri().path());
+
+        if limits::exceeds(req.headers(), limits.request) {
+            return RequestHandler::payload_too_large(Instant::now())
+        }
+
+        let mut container = match middlewares.request_body_required() {
+            true => {
+                let timer = Instant::now();
+
+                match ContainerHandler::new(req, limits.request).await {
+                    Ok(val) => val,
+                    Err(err) if limits::is_too_large(&err) => return RequestHandler::payload_too_large(timer),
+                    Err(err) => return Err(err)
+                }
+            },
+            false => ContainerHandler::streaming(req, limits.request)
+        };
+        container.middleware_body_limit_set(config.max_middleware_body_bytes);
         let middlewares = middlewares.to_owned();
-        let backend_timeout = Duration::from_millis(config.backend.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MILLIS) as u64);
 
-        for client in middlewares.request() {
-            let timer = Instant::now();
+        match RequestHandler::request_stage(&mut container, &middlewares).await? {
+            StageResult::Completed => (),
+            StageResult::Stopped => return RequestHandler::handle_request_stop(container, &middlewares, &config).await,
+            StageResult::Responded => return RequestHandler::handle_synthetic_response(container, &middlewares).await,
+            StageResult::Unavailable => return RequestHandler::service_unavailable_error(container.timer())
+        };
 
-            match client.connection().clone() {
-                Some(mut connection) => {
-                    let timeout = [client.timeout().as_millis().to_string(), "m".to_string()].join("");
-                    let mut request = tonic::Request::new(container.into_middleware_request()?);
-                    let metadata = request.metadata_mut();
-                    metadata.insert(GRPC_TIMEOUT_HEADER, MetadataValue::from_str(timeout.as_str())?);
+        let backend = match RequestHandler::resolve_backend(&config, &container) {
+            Some(val) => val,
+            None => return RequestHandler::handle_error(container, &middlewares, ErrorKind::UnknownBackend, "Unknown backend").await
+        };
+        let backend_timeout = RequestHandler::backend_timeout(&backend);
 
-                    match tokio::time::timeout(client.timeout(), connection.handle_request(request)).await {
-                        Ok(val) => {
-                            match val {
-                                Ok(response) => {
-                                    let data = response.into_inner();
+        container.state_set(BackendResponse);
 
-                                    match ResponseStatus::from_i32(data.status) {
-                                        Some(ResponseStatus::Success) => container.handle_middleware_request(&data, false)?,
-                                        Some(ResponseStatus::Continue) => (),
-                                        Some(ResponseStatus::Stop) => {
-                                            container.handle_middleware_request(&data, true)?;
+        let backend_timer = Instant::now();
 
-                                            return Ok(container.into_response()?)
-                                        },
-                                        None => ()
-                                    };
-                                },
-                                Err(err) => {
-                                    error!("[Middleware Request] Failed to get response from {}: {:?}", client.url(), err);
+        let mut request = container.into_request(upstream.url(&backend).as_str())?;
+        *request.version_mut() = RequestHandler::backend_version(&backend, request.version());
 
-                                    return Ok(RequestHandler::service_unavailable_error(container.timer())?)
-                                }
-                            }
-                        },
-                        Err(_err) => {
-                            error!("[Middleware Request] Timed out {}: elapsed {} ms.", client.url(), client.timeout().as_millis());
+        match tokio::time::timeout(backend_timeout, upstream.send(request)).await {
+            Ok(val) => {
+                match val {
+                    Ok(data) => {
+                        container.backend_elapsed_set(backend_timer.elapsed());
+
+                        if limits::exceeds(data.headers(), limits.response) {
+                            error!("[Backend] Response body exceeds the limit of {} bytes.", limits.response.unwrap_or(0));
+
+                            return RequestHandler::handle_error(container, &middlewares, ErrorKind::ResponseTooLarge, "Response body is too large").await
+                        }
+
+                        match container.handle_response(data, middlewares.response_body_required(), limits.response).await {
+                            Ok(_) => (),
+                            Err(err) if limits::is_too_large(&err) => {
+                                error!("[Backend] {}.", err);
 
-                            return Ok(RequestHandler::service_unavailable_error(container.timer())?)
+                                return RequestHandler::handle_error(container, &middlewares, ErrorKind::ResponseTooLarge, &err.to_string()).await
+                            },
+                            Err(err) => return Err(err)
                         }
+                    },
+                    // Streamed request body went over the limit while it was sent
+                    Err(err) if limits::is_too_large(&err) => {
+                        error!("[Backend] Request body exceeds the limit of {} bytes.", limits.request.unwrap_or(0));
+
+                        return RequestHandler::payload_too_large(container.timer())
+                    },
+                    Err(err) => {
+                        error!("[Backend] Failed to get response from backend. {}", err);
+
+                        return RequestHandler::handle_error(container, &middlewares, ErrorKind::BackendUnavailable, &err.to_string()).await
                     }
-                },
-                None => {
-                    error!("[Middleware Request] Endpoint is not resolved. {}", client.url());
-                    return Ok(RequestHandler::service_unavailable_error(container.timer())?)
                 }
+            },
+            Err(_err) => {
+                error!("[Backend] Timed out after {} ms.", backend_timeout.as_millis());
+
+                return RequestHandler::handle_error(container, &middlewares, ErrorKind::BackendTimeout, "Backend timed out").await
+            }
+        }
+
+        info!("[Backend Request] took {} ms.", container.backend_elapsed().unwrap_or(Duration::from_millis(0)).as_millis());
+        container.state_set(MiddlewareResponse);
+
+        match RequestHandler::response_stage(&mut container, &middlewares).await? {
+            StageResult::Completed | StageResult::Stopped | StageResult::Responded => Ok(container.into_response()?),
+            StageResult::Unavailable => Ok(RequestHandler::service_unavailable_error(container.timer())?)
+        }
+    }
+
+    // Depending on the pipeline config a STOP either ends everything, or the synthetic response goes
+    // through the response stage, so e.g. auditing and CORS middlewares can still observe and decorate it.
+    async fn handle_request_stop(mut container: ContainerHandler, middlewares: &Middlewares, config: &Config) -> Result<Response<Body>, GenericError> {
+        match RequestHandler::stop_runs_response_stage(config) {
+            true => RequestHandler::handle_synthetic_response(container, middlewares).await,
+            false => Ok(container.into_response()?)
+        }
+    }
+
+    fn stop_runs_response_stage(config: &Config) -> bool {
+        config.pipeline.as_ref().and_then(|x| x.request_stop.as_ref()) == Some(&RequestStop::ResponseStage)
+    }
+
+    // Response produced in the request stage (STOP or RESPOND) goes through the response stage instead of the backend one
+    async fn handle_synthetic_response(mut container: ContainerHandler, middlewares: &Middlewares) -> Result<Response<Body>, GenericError> {
+        match RequestHandler::response_stage(&mut container, middlewares).await? {
+            StageResult::Unavailable => Ok(RequestHandler::service_unavailable_error(container.timer())?),
+            _ => Ok(container.into_response()?)
+        }
+    }
+
+    // Without error middlewares the hard-coded responses are returned, otherwise they are the starting point
+    // the middlewares can replace (branded error page, stale cached response, ...).
+    async fn handle_error(mut container: ContainerHandler, middlewares: &Middlewares, kind: ErrorKind, message: &str) -> Result<Response<Body>, GenericError> {
+        let (status_code, body) = match kind {
+            ErrorKind::BackendTimeout => (504, "Gateway Timeout"),
+            _ => (502, "Bad Gateway")
+        };
+
+        if middlewares.error().is_empty() {
+            return match kind {
+                ErrorKind::BackendTimeout => RequestHandler::gateway_timeout(container.timer()),
+                _ => RequestHandler::gateway_error(container.timer())
+            }
+        }
+
+        container.error_set(status_code, body);
+
+        for client in middlewares.error() {
+            let timer = Instant::now();
+
+            match client.handler() {
+                Some(handler) => {
+                    match tokio::time::timeout(client.timeout(), handler.error(&mut container, client, kind, message)).await {
+                        Ok(Ok(data)) => {
+                            match ResponseStatus::from_i32(data.status) {
+                                Some(ResponseStatus::Success) => container.handle_middleware_response(&data, false)?,
+                                Some(ResponseStatus::Continue) | None => (),
+                                Some(_) => {
+                                    container.handle_middleware_response(&data, false)?;
+                                    break
+                                }
+                            };
+                        },
+                        Ok(Err(err)) => error!("[Middleware Error] Failed to get response from {}: {:?}", client.url(), err),
+                        Err(_err) => error!("[Middleware Error] Timed out {}: elapsed {} ms.", client.url(), client.timeout().as_millis())
+                    }
+                },
+                None => error!("[Middleware Error] Endpoint is not resolved. {}", client.url())
             };
 
-            info!("[Middleware Request] {} took {} ms.", client.url(), timer.elapsed().as_millis());
+            info!("[Middleware Error] {} took {} ms.", client.url(), timer.elapsed().as_millis());
         }
 
+        container.into_response()
+    }
+
+    // gRPC is proxied over HTTP/2 end-to-end without buffering, request middlewares only see the metadata
+    // and the response stage is skipped, as the messages and trailers are streamed back as they arrive.
+    // Body limits don't apply, a streaming call can carry any number of messages.
+    async fn handle_grpc(req: Request<Body>, middlewares: Arc<Middlewares>, config: Config, upstream: Arc<dyn Upstream>) -> Result<Response<Body>, GenericError> {
+        let mut container = ContainerHandler::streaming(req, None);
+
+        match RequestHandler::request_stage(&mut container, &middlewares).await? {
+            StageResult::Completed => (),
+            // Response stage is skipped for gRPC, so RESPOND can't do more than STOP
+            StageResult::Stopped | StageResult::Responded => return container.grpc_error_response(),
+            StageResult::Unavailable => return grpc::error_response(Code::Unavailable, "Service Unavailable")
+        };
+
+        let backend = match RequestHandler::resolve_backend(&config, &container) {
+            Some(val) => val,
+            None => return grpc::error_response(Code::Unavailable, "Bad Gateway")
+        };
+        let backend_timeout = RequestHandler::backend_timeout(&backend);
+
         container.state_set(BackendResponse);
 
         let backend_timer = Instant::now();
 
-        match tokio::time::timeout(backend_timeout, http_client.request(container.into_request()?)).await {
+        match tokio::time::timeout(backend_timeout, upstream.send(container.grpc_request(upstream.url(&backend).as_str())?)).await {
             Ok(val) => {
                 match val {
                     Ok(data) => {
                         container.backend_elapsed_set(backend_timer.elapsed());
-                        container.handle_response(data).await?;
+
+                        Ok(container.passthrough_response(data)?)
                     },
                     Err(err) => {
-                        error!("[Backend] Failed to get response from backend. {}", err);
+                        error!("[Backend] Failed to get response from gRPC backend. {}", err);
 
-                        return Ok(RequestHandler::gateway_error(container.timer())?)
+                        Ok(grpc::error_response(Code::Unavailable, "Bad Gateway")?)
                     }
                 }
             },
             Err(_err) => {
                 error!("[Backend] Timed out after {} ms.", backend_timeout.as_millis());
 
-                return Ok(RequestHandler::gateway_timeout(container.timer())?)
+                Ok(grpc::error_response(Code::DeadlineExceeded, "Gateway Timeout")?)
             }
         }
+    }
 
-        info!("[Backend Request] took {} ms.", container.backend_elapsed().unwrap_or(Duration::from_millis(0)).as_millis());
-        container.state_set(MiddlewareResponse);
+    async fn request_stage(container: &mut ContainerHandler, middlewares: &Middlewares) -> HandlerResult<StageResult> {
+        for client in middlewares.request() {
+            let timer = Instant::now();
+
+            match client.handler() {
+                Some(handler) => {
+                    let call = handler.request(container, client);
+
+                    match tokio::time::timeout(client.timeout(), call).await {
+                        Ok(val) => {
+                            match val {
+                                Ok(data) => {
+                                    match data.status() {
+                                        Some(ResponseStatus::Success) => container.handle_request_answer(&data, false)?,
+                                        Some(ResponseStatus::Continue) => (),
+                                        Some(ResponseStatus::Stop) => {
+                                            // Response is built apart from the request, a v1 STOP without body answers an empty one
+                                            // whether the request body was buffered or streamed
+                                            container.state_set(MiddlewareResponse);
+                                            container.handle_request_answer(&data, true)?;
+
+                                            return Ok(StageResult::Stopped)
+                                        },
+                                        Some(ResponseStatus::Respond) => {
+                                            container.state_set(MiddlewareResponse);
+                                            container.handle_request_answer(&data, true)?;
+                                            container.status_code_default(200);
+
+                                            return Ok(StageResult::Responded)
+                                        },
+                                        Some(ResponseStatus::SkipStage) => {
+                                            container.handle_request_answer(&data, false)?;
+
+                                            return Ok(StageResult::Completed)
+                                        },
+                                        None => ()
+                                    };
+                                },
+                                Err(err) => {
+                                    error!("[Middleware Request] Failed to get response from {}: {:?}", client.url(), err);
 
+                                    return Ok(StageResult::Unavailable)
+                                }
+                            }
+                        },
+                        Err(_err) => {
+                            error!("[Middleware Request] Timed out {}: elapsed {} ms.", client.url(), client.timeout().as_millis());
+
+                            return Ok(StageResult::Unavailable)
+                        }
+                    }
+                },
+                None => {
+                    error!("[Middleware Request] Endpoint is not resolved. {}", client.url());
+                    return Ok(StageResult::Unavailable)
+                }
+            };
+
+            info!("[Middleware Request] {} took {} ms.", client.url(), timer.elapsed().as_millis());
+        }
+
+        Ok(StageResult::Completed)
+    }
+
+    async fn response_stage(container: &mut ContainerHandler, middlewares: &Middlewares) -> HandlerResult<StageResult> {
         for client in middlewares.response() {
             let timer = Instant::now();
 
-            match client.connection().clone() {
-                Some(mut connection) => {
-                    let timeout = [client.timeout().as_millis().to_string(), "m".to_string()].join("");
-                    let mut request = tonic::Request::new(container.into_middleware_response()?);
-                    let metadata = request.metadata_mut();
-                    metadata.insert(GRPC_TIMEOUT_HEADER, MetadataValue::from_str(timeout.as_str())?);
+            match client.handler() {
+                Some(handler) => {
+                    let call = handler.response(container, client);
 
-                    match tokio::time::timeout(client.timeout(), connection.handle_response(request)).await {
+                    match tokio::time::timeout(client.timeout(), call).await {
                         Ok(val) => {
                             match val {
-                                Ok(response) => {
-                                    let data = response.into_inner();
-
-                                    match ResponseStatus::from_i32(data.status) {
-                                        Some(ResponseStatus::Success) => container.handle_middleware_response(&data, false)?,
+                                Ok(data) => {
+                                    match data.status() {
+                                        Some(ResponseStatus::Success) | Some(ResponseStatus::Respond) => container.handle_response_answer(&data, false)?,
                                         Some(ResponseStatus::Continue) => (),
                                         Some(ResponseStatus::Stop) => {
-                                            container.handle_middleware_response(&data, true)?;
+                                            container.handle_response_answer(&data, true)?;
 
-                                            return Ok(container.into_response()?)
+                                            return Ok(StageResult::Stopped)
+                                        },
+                                        Some(ResponseStatus::SkipStage) => {
+                                            container.handle_response_answer(&data, false)?;
+
+                                            return Ok(StageResult::Completed)
                                         },
                                         None => ()
                                     }
@@ -173,32 +418,32 @@ impl RequestHandler {
                                 Err(err) => {
                                     error!("[Middleware Response] Failed to get response from {}: {:?}", client.url(), err);
 
-                                    return Ok(RequestHandler::service_unavailable_error(container.timer())?)
+                                    return Ok(StageResult::Unavailable)
                                 }
                             }
                         },
                         Err(_err) => {
                             error!("[Middleware Response] Timed out {}: elapsed {} ms.", client.url(), client.timeout().as_millis());
 
-                            return Ok(RequestHandler::service_unavailable_error(container.timer())?)
+                            return Ok(StageResult::Unavailable)
                         }
                     }
                 },
                 None => {
                     error!("[Middleware Response] Endpoint is not resolved. {}", client.url());
-                    return Ok(RequestHandler::service_unavailable_error(container.timer())?)
+                    return Ok(StageResult::Unavailable)
                 }
             };
 
             info!("[Middleware Response] {} took {} ms.", client.url(), timer.elapsed().as_millis());
         }
 
-        Ok(container.into_response()?)
+        Ok(StageResult::Completed)
     }
 }
 
 impl Service<Request<Body>> for RequestHandler {
-    type Response = Response<Body>;
+    type Response = Response<TrailersBody>;
     type Error = hyper::Error;
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
 
@@ -207,17 +452,21 @@ impl Service<Request<Body>> for RequestHandler {
     }
 
     fn call(&mut self, req: Request<Body>) -> Self::Future {
+        if self.config.readiness_path.as_deref() == Some(req.uri().path()) {
+            return Box::pin(future::ok(trailers::attach(RequestHandler::readiness(self.ready.load(Ordering::SeqCst)))))
+        }
+
         let middlewares = Arc::clone(&self.middlewares);
         let config = self.config.clone();
-        let http_client = self.http_client.clone();
+        let upstream = Arc::clone(&self.upstream);
 
         let executor = async move {
-            match RequestHandler::handle(req, middlewares, config, http_client).await {
-                Ok(val) => Ok(val),
+            match RequestHandler::handle(req, middlewares, config, upstream).await {
+                Ok(val) => Ok(trailers::attach(val)),
                 Err(err) => {
                     error!("Failed to parse request: {:?}", err);
 
-                    Ok(RequestHandler::generic_error())
+                    Ok(trailers::attach(RequestHandler::generic_error()))
                 }
             }
         };
@@ -0,0 +1,118 @@
+use rhai::{Engine, AST, Scope, Dynamic, Shared, Module};
+use rhai::packages::{Package, StandardPackage};
+use rhai::serde::{to_dynamic, from_dynamic};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::time::{Duration, Instant};
+use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse};
+use crate::container_handler::{ContainerHandler, RequestAnswer, ResponseAnswer};
+use crate::middleware::Middleware;
+use crate::handler::MiddlewareHandler;
+use crate::config::MiddlewareConfig;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+type GenericError = Box<dyn std::error::Error + Send + Sync>;
+type Result<T> = std::result::Result<T, GenericError>;
+
+pub const DEFAULT_MAX_OPERATIONS: u64 = 1_000_000;
+pub const DEFAULT_MAX_STRING_SIZE: usize = 1024 * 1024;
+pub const DEFAULT_MAX_ARRAY_SIZE: usize = 10_000;
+pub const DEFAULT_MAX_MAP_SIZE: usize = 10_000;
+
+const MAX_CALL_LEVELS: usize = 32;
+
+pub const REQUEST_FN: &str = "request";
+pub const RESPONSE_FN: &str = "response";
+
+// Script is compiled once, every call gets its own engine so the timeout applies per call
+#[derive(Clone)]
+pub struct Script {
+    url: String,
+    ast: Arc<AST>,
+    package: Shared<Module>,
+    max_operations: u64,
+    max_string_size: usize,
+    max_array_size: usize,
+    max_map_size: usize
+}
+
+impl Script {
+    // Limits come from the middleware config, max_fuel is the operations limit
+    pub fn load(middleware: &MiddlewareConfig) -> Result<Script> {
+        let source = std::fs::read_to_string(middleware.url.trim_start_matches("file://"))?;
+        let ast = Engine::new_raw().compile(source)?;
+
+        Ok(Script {
+            url: middleware.url.clone(),
+            ast: Arc::new(ast),
+            package: StandardPackage::new().as_shared_module(),
+            max_operations: middleware.max_fuel.unwrap_or(DEFAULT_MAX_OPERATIONS),
+            max_string_size: middleware.max_string_size.unwrap_or(DEFAULT_MAX_STRING_SIZE),
+            max_array_size: middleware.max_array_size.unwrap_or(DEFAULT_MAX_ARRAY_SIZE),
+            max_map_size: middleware.max_map_size.unwrap_or(DEFAULT_MAX_MAP_SIZE)
+        })
+    }
+
+    // Stages default to the functions the script defines
+    pub fn defines(&self, name: &str) -> bool {
+        self.ast.iter_functions().any(|x| x.name == name)
+    }
+
+    // Messages are passed as maps in the JSON shape of HTTP middlewares, the answer is a map of the same shape
+    fn handle_request(&self, message: &RequestRequest, timeout: Duration) -> Result<RequestResponse> {
+        self.call(REQUEST_FN, message, timeout)
+    }
+
+    fn handle_response(&self, message: &ResponseRequest, timeout: Duration) -> Result<ResponseResponse> {
+        self.call(RESPONSE_FN, message, timeout)
+    }
+
+    fn call<T: Serialize, R: DeserializeOwned>(&self, name: &str, message: &T, timeout: Duration) -> Result<R> {
+        let engine = self.engine(timeout);
+        let answer: Dynamic = engine.call_fn(&mut Scope::new(), &self.ast, name, (to_dynamic(message)?,))?;
+
+        Ok(from_dynamic(&answer)?)
+    }
+
+    // Raw engine, so there are no module imports nor stdout, sync code can't be cancelled so the timeout is checked as it runs
+    fn engine(&self, timeout: Duration) -> Engine {
+        let mut engine = Engine::new_raw();
+        engine.register_global_module(self.package.clone());
+        engine.set_max_operations(self.max_operations);
+        engine.set_max_call_levels(MAX_CALL_LEVELS);
+        engine.set_max_string_size(self.max_string_size);
+        engine.set_max_array_size(self.max_array_size);
+        engine.set_max_map_size(self.max_map_size);
+        engine.disable_symbol("eval");
+
+        let url = self.url.clone();
+        engine.on_print(move |x| debug!("[{}] {}", url, x));
+
+        let started = Instant::now();
+        engine.on_progress(move |_| match started.elapsed() > timeout {
+            true => Some(Dynamic::from("Script timed out")),
+            false => None
+        });
+
+        engine
+    }
+}
+
+// Calls run on the blocking pool, so they don't hold up the runtime while the engine checks the timeout
+#[async_trait]
+impl MiddlewareHandler for Script {
+    async fn request(&self, container: &mut ContainerHandler, middleware: &Middleware) -> Result<RequestAnswer> {
+        let message = container.into_middleware_request(middleware)?;
+        let (script, timeout) = (self.clone(), middleware.timeout());
+
+        Ok(RequestAnswer::V1(tokio::task::spawn_blocking(move || script.handle_request(&message, timeout)).await??))
+    }
+
+    async fn response(&self, container: &mut ContainerHandler, middleware: &Middleware) -> Result<ResponseAnswer> {
+        let message = container.into_middleware_response(middleware)?;
+        let (script, timeout) = (self.clone(), middleware.timeout());
+
+        Ok(ResponseAnswer::V1(tokio::task::spawn_blocking(move || script.handle_response(&message, timeout)).await??))
+    }
+}
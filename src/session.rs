@@ -0,0 +1,86 @@
+use tonic::transport::Channel;
+use tonic::codec::Streaming;
+use tokio::sync::mpsc;
+use crate::kubeware::middleware_client::MiddlewareClient;
+use crate::kubeware::{RequestRequest, ResponseRequest, SessionEvent, SessionDecision};
+use crate::kubeware::session_event::Event;
+use crate::kubeware::session_decision::Decision;
+use crate::container_handler::{RequestAnswer, ResponseAnswer};
+use crate::body_chunks;
+use bytes::Bytes;
+
+type GenericError = Box<dyn std::error::Error + Send + Sync>;
+type Result<T> = std::result::Result<T, GenericError>;
+
+const SESSION_BUFFER: usize = 16;
+
+// Stream lives as long as the request, dropping it closes the middleware side
+pub struct Session {
+    events: mpsc::Sender<SessionEvent>,
+    decisions: Streaming<SessionDecision>
+}
+
+impl Session {
+    // First event is queued before opening, so the middleware may wait for it before answering
+    async fn open(connection: &mut MiddlewareClient<Channel>, event: Event) -> Result<Session> {
+        let (mut events, receiver) = mpsc::channel(SESSION_BUFFER);
+        events.send(SessionEvent { event: Some(event) }).await?;
+
+        let decisions = connection.session(tonic::Request::new(receiver)).await?.into_inner();
+
+        Ok(Session { events, decisions })
+    }
+
+    async fn start(session: Option<Session>, connection: &mut MiddlewareClient<Channel>, event: Event) -> Result<Session> {
+        match session {
+            Some(mut val) => {
+                val.events.send(SessionEvent { event: Some(event) }).await?;
+                Ok(val)
+            },
+            None => Session::open(connection, event).await
+        }
+    }
+
+    // Body follows its event in chunks
+    async fn send_body(&mut self, body: String) -> Result<()> {
+        for chunk in body_chunks::chunks(Bytes::from(body)) {
+            self.events.send(SessionEvent { event: Some(Event::Chunk(chunk)) }).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn decision(&mut self) -> Result<Decision> {
+        match self.decisions.message().await? {
+            Some(SessionDecision { decision: Some(val) }) => Ok(val),
+            Some(_) => Err("Session decision is empty".into()),
+            None => Err("Session was closed by the middleware".into())
+        }
+    }
+}
+
+// Request body is sent as chunks instead of inline
+pub async fn request(session: Option<Session>, connection: &mut MiddlewareClient<Channel>, mut message: RequestRequest) -> Result<(Session, RequestAnswer)> {
+    let body = std::mem::take(&mut message.body);
+    let mut session = Session::start(session, connection, Event::Request(message)).await?;
+    session.send_body(body).await?;
+
+    match session.decision().await? {
+        Decision::Request(val) => Ok((session, RequestAnswer::V1(val))),
+        Decision::RequestV2(val) => Ok((session, RequestAnswer::V2(val))),
+        _ => Err("Session answered a request event with a response decision".into())
+    }
+}
+
+// Response body is sent as chunks, request body stays inline for middlewares which skipped the request stage
+pub async fn response(session: Option<Session>, connection: &mut MiddlewareClient<Channel>, mut message: ResponseRequest) -> Result<(Session, ResponseAnswer)> {
+    let body = std::mem::take(&mut message.response_body);
+    let mut session = Session::start(session, connection, Event::Response(message)).await?;
+    session.send_body(body).await?;
+
+    match session.decision().await? {
+        Decision::Response(val) => Ok((session, ResponseAnswer::V1(val))),
+        Decision::ResponseV2(val) => Ok((session, ResponseAnswer::V2(val))),
+        _ => Err("Session answered a response event with a request decision".into())
+    }
+}
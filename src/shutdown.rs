@@ -0,0 +1,72 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use futures::channel::oneshot;
+use futures::future;
+use crate::config::Config;
+
+pub use kubeware_sdk::sigterm_signal;
+
+const DEFAULT_PRE_STOP_DELAY_MILLIS: u32 = 0;
+const DEFAULT_DRAIN_TIMEOUT_MILLIS: u32 = 30_000;
+
+pub struct Shutdown {
+    ready: Arc<AtomicBool>,
+    pre_stop_delay: Duration,
+    drain_timeout: Duration
+}
+
+impl Shutdown {
+    pub fn with_config(config: &Config) -> Shutdown {
+        let (pre_stop_delay, drain_timeout) = match &config.shutdown {
+            Some(val) => (val.pre_stop_delay_ms, val.drain_timeout_ms),
+            None => (None, None)
+        };
+
+        Shutdown {
+            ready: Arc::new(AtomicBool::new(true)),
+            pre_stop_delay: Duration::from_millis(pre_stop_delay.unwrap_or(DEFAULT_PRE_STOP_DELAY_MILLIS) as u64),
+            drain_timeout: Duration::from_millis(drain_timeout.unwrap_or(DEFAULT_DRAIN_TIMEOUT_MILLIS) as u64)
+        }
+    }
+
+    pub fn ready(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.ready)
+    }
+
+    // Returns the future to pass to `with_graceful_shutdown` and the drain deadline.
+    // Once triggered readiness is flipped first, so the pod is taken out of the endpoints while
+    // connections are still accepted during the pre-stop delay. After that the server stops accepting
+    // and in-flight requests (with their middleware calls) have until the deadline to finish.
+    pub fn start<T>(&self, trigger: T) -> (impl Future<Output = ()>, impl Future<Output = ()>)
+        where T: Future<Output = ()> {
+
+        let ready = self.ready();
+        let pre_stop_delay = self.pre_stop_delay;
+        let drain_timeout = self.drain_timeout;
+        let (drain_tx, drain_rx) = oneshot::channel::<()>();
+
+        let stop_accepting = async move {
+            trigger.await;
+
+            info!("Shutting down, waiting {} ms before draining connections.", pre_stop_delay.as_millis());
+            ready.store(false, Ordering::SeqCst);
+
+            if pre_stop_delay > Duration::from_millis(0) {
+                tokio::time::delay_for(pre_stop_delay).await;
+            }
+
+            let _ = drain_tx.send(());
+        };
+
+        let deadline = async move {
+            match drain_rx.await {
+                Ok(_) => tokio::time::delay_for(drain_timeout).await,
+                Err(_) => future::pending::<()>().await
+            }
+        };
+
+        (stop_accepting, deadline)
+    }
+}
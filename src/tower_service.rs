@@ -1,6 +1,5 @@
 use std::sync::{Arc, Mutex};
-use hyper::{Client};
-use hyper::client::HttpConnector;
+use std::sync::atomic::AtomicBool;
 use hyper::service::Service;
 use std::task::{Context, Poll};
 use futures::future;
@@ -8,13 +7,15 @@ use futures::future;
 use crate::middlewares::Middlewares;
 use crate::request_handler::RequestHandler;
 use crate::config::Config;
+use crate::upstream::Upstream;
 
 pub struct Builder
 {
-    pub http_client: Client<HttpConnector>,
+    pub upstream: Arc<dyn Upstream>,
     pub middlewares: Arc<Middlewares>,
     pub config: Config,
-    pub mutex: Mutex<bool>
+    pub mutex: Mutex<bool>,
+    pub ready: Arc<AtomicBool>
 }
 
 impl<T> Service<T> for Builder {
@@ -27,7 +28,7 @@ impl<T> Service<T> for Builder {
         
         match self.mutex.lock() {
             Ok(_) => {
-                if middlewares.all().iter().any(|x| x.connection().is_none()) {
+                if middlewares.all().iter().any(|x| !x.resolved()) {
                     debug!("Trying to reconnect to unreachable hosts...");
 
                     // Is there another way to do this ? :(
@@ -50,8 +51,9 @@ impl<T> Service<T> for Builder {
     fn call(&mut self, _: T) -> Self::Future {
         future::ok(RequestHandler {
             middlewares: self.middlewares.clone(),
-            http_client: self.http_client.clone(),
-            config: self.config.clone()
+            upstream: Arc::clone(&self.upstream),
+            config: self.config.clone(),
+            ready: Arc::clone(&self.ready)
         })
     }
 }
\ No newline at end of file
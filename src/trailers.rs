@@ -0,0 +1,59 @@
+use hyper::{Body, HeaderMap, Response};
+use hyper::body::HttpBody;
+use http_body::SizeHint;
+use bytes::Bytes;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+// Trailers set by middlewares, carried as a response extension until the response leaves kubeware
+pub struct Trailers(pub HeaderMap);
+
+// Hyper bodies can't be given trailers, so they are appended by wrapping the body
+pub struct TrailersBody {
+    inner: Body,
+    trailers: Option<HeaderMap>
+}
+
+impl HttpBody for TrailersBody {
+    type Data = Bytes;
+    type Error = hyper::Error;
+
+    fn poll_data(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Pin::new(&mut self.inner).poll_data(cx)
+    }
+
+    // Backend trailers are kept, middleware ones win on conflicts
+    fn poll_trailers(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let inner = match Pin::new(&mut self.inner).poll_trailers(cx) {
+            Poll::Ready(Ok(val)) => val,
+            other => return other
+        };
+
+        match (inner, self.trailers.take()) {
+            (Some(mut val), Some(added)) => {
+                for (name, value) in added.iter() {
+                    val.insert(name.clone(), value.clone());
+                }
+
+                Poll::Ready(Ok(Some(val)))
+            },
+            (val, added) => Poll::Ready(Ok(val.or(added)))
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.trailers.is_none() && self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+// Trailers only reach HTTP/2 clients, HTTP/1 responses drop them
+pub fn attach(response: Response<Body>) -> Response<TrailersBody> {
+    let (mut metadata, body) = response.into_parts();
+    let trailers = metadata.extensions.remove::<Trailers>().map(|x| x.0);
+
+    Response::from_parts(metadata, TrailersBody { inner: body, trailers })
+}
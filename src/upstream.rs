@@ -0,0 +1,94 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use futures::future;
+use hyper::{Client, Request, Body, Response, Version};
+use hyper::client::HttpConnector;
+use tower_service::Service;
+use crate::config::Backend;
+
+type GenericError = Box<dyn std::error::Error + Send + Sync>;
+type Result<T> = std::result::Result<T, GenericError>;
+
+pub type ResponseFuture = Pin<Box<dyn Future<Output = Result<Response<Body>>> + Send>>;
+
+// Where the pipeline sends the request once the request stage is done
+pub trait Upstream: Send + Sync {
+    // Request path and query are appended to it
+    fn url(&self, backend: &Backend) -> String;
+
+    fn send(&self, request: Request<Body>) -> ResponseFuture;
+}
+
+// Configured backends, reached over the network when kubeware runs as a proxy
+pub struct Clients {
+    http_client: Client<HttpConnector>,
+    http2_client: Client<HttpConnector>
+}
+
+impl Clients {
+    pub fn new() -> Clients {
+        Clients {
+            http_client: Client::new(),
+            http2_client: Client::builder().http2_only(true).build_http()
+        }
+    }
+}
+
+impl Default for Clients {
+    fn default() -> Clients {
+        Clients::new()
+    }
+}
+
+impl Upstream for Clients {
+    fn url(&self, backend: &Backend) -> String {
+        backend.url.clone()
+    }
+
+    // Version is already set from the backend config, HTTP/2 ones (and gRPC) go through http2_client
+    fn send(&self, request: Request<Body>) -> ResponseFuture {
+        let client = match request.version() {
+            Version::HTTP_2 => self.http2_client.clone(),
+            _ => self.http_client.clone()
+        };
+
+        Box::pin(async move { Ok(client.request(request).await?) })
+    }
+}
+
+// Service wrapped by the tower layer, it gets the request the way a server would (relative URI).
+// Backend urls from the config are ignored, their timeouts still apply.
+pub struct Inner<S> {
+    service: Mutex<S>
+}
+
+impl<S> Inner<S> {
+    pub fn new(service: S) -> Inner<S> {
+        Inner { service: Mutex::new(service) }
+    }
+}
+
+impl<S> Upstream for Inner<S>
+    where S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+          S::Future: Send,
+          S::Error: Into<GenericError> {
+
+    fn url(&self, _backend: &Backend) -> String {
+        String::new()
+    }
+
+    // Every request gets its own clone, so it can wait for readiness without holding the lock
+    fn send(&self, request: Request<Body>) -> ResponseFuture {
+        let mut service = match self.service.lock() {
+            Ok(val) => val.clone(),
+            Err(err) => return Box::pin(future::err(err.to_string().into()))
+        };
+
+        Box::pin(async move {
+            future::poll_fn(|cx| service.poll_ready(cx)).await.map_err(Into::into)?;
+
+            service.call(request).await.map_err(Into::into)
+        })
+    }
+}
@@ -0,0 +1,128 @@
+use wasmi::{Engine, Module, Store, StoreLimits, StoreLimitsBuilder, Linker, Config};
+use prost::Message;
+use crate::kubeware::{RequestRequest, RequestResponse, RequestResponseV2, ResponseRequest, ResponseResponse, ResponseResponseV2};
+use crate::container_handler::{ContainerHandler, RequestAnswer, ResponseAnswer};
+use crate::middleware::Middleware;
+use crate::handler::MiddlewareHandler;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+type GenericError = Box<dyn std::error::Error + Send + Sync>;
+type Result<T> = std::result::Result<T, GenericError>;
+
+pub const DEFAULT_MAX_FUEL: u64 = 10_000_000;
+pub const DEFAULT_MAX_MEMORY_BYTES: u64 = 16 * 1024 * 1024;
+
+const MEMORY_EXPORT: &str = "memory";
+const ALLOC_EXPORT: &str = "kubeware_alloc";
+pub const REQUEST_EXPORT: &str = "kubeware_request";
+pub const RESPONSE_EXPORT: &str = "kubeware_response";
+
+// Module is compiled once, every call gets a fresh instance so plugins keep no state between requests.
+// Engine and module are shared, clones are cheap.
+#[derive(Clone)]
+pub struct Plugin {
+    engine: Engine,
+    module: Arc<Module>,
+    max_fuel: u64,
+    max_memory_bytes: usize
+}
+
+impl Plugin {
+    pub fn load(path: &str, max_fuel: Option<u64>, max_memory_bytes: Option<u64>) -> Result<Plugin> {
+        let bytes = std::fs::read(path.trim_start_matches("file://"))?;
+
+        let mut config = Config::default();
+        config.consume_fuel(true);
+
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, &bytes)?;
+
+        Ok(Plugin {
+            engine,
+            module: Arc::new(module),
+            max_fuel: max_fuel.unwrap_or(DEFAULT_MAX_FUEL),
+            max_memory_bytes: max_memory_bytes.unwrap_or(DEFAULT_MAX_MEMORY_BYTES) as usize
+        })
+    }
+
+    // Stages default to the hooks the module exports
+    pub fn exports(&self, name: &str) -> bool {
+        self.module.get_export(name).is_some()
+    }
+
+    // Hooks get the encoded proto message and return the encoded answer, in the format of the api version
+    fn handle_request(&self, message: &RequestRequest, v2: bool) -> Result<RequestAnswer> {
+        let output = self.call(REQUEST_EXPORT, encode(message)?)?;
+
+        match v2 {
+            true => Ok(RequestAnswer::V2(RequestResponseV2::decode(output.as_slice())?)),
+            false => Ok(RequestAnswer::V1(RequestResponse::decode(output.as_slice())?))
+        }
+    }
+
+    fn handle_response(&self, message: &ResponseRequest, v2: bool) -> Result<ResponseAnswer> {
+        let output = self.call(RESPONSE_EXPORT, encode(message)?)?;
+
+        match v2 {
+            true => Ok(ResponseAnswer::V2(ResponseResponseV2::decode(output.as_slice())?)),
+            false => Ok(ResponseAnswer::V1(ResponseResponse::decode(output.as_slice())?))
+        }
+    }
+
+    // Input is written to memory from kubeware_alloc, the hook returns the output location as (pointer << 32) | length
+    fn call(&self, hook: &str, input: Vec<u8>) -> Result<Vec<u8>> {
+        let limits = StoreLimitsBuilder::new().memory_size(self.max_memory_bytes).build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|x: &mut StoreLimits| x);
+        store.set_fuel(self.max_fuel).map_err(|err| err.to_string())?;
+
+        let instance = Linker::<StoreLimits>::new(&self.engine)
+            .instantiate(&mut store, &self.module)?
+            .start(&mut store)?;
+
+        let memory = instance.get_memory(&store, MEMORY_EXPORT).ok_or("Plugin does not export its memory")?;
+        let alloc = instance.get_typed_func::<i32, i32>(&store, ALLOC_EXPORT)?;
+        let hook = instance.get_typed_func::<(i32, i32), i64>(&store, hook)?;
+
+        let pointer = alloc.call(&mut store, input.len() as i32)?;
+        memory.write(&mut store, pointer as u32 as usize, &input).map_err(|err| err.to_string())?;
+
+        let location = hook.call(&mut store, (pointer, input.len() as i32))? as u64;
+        let length = (location & 0xffff_ffff) as usize;
+
+        if length > self.max_memory_bytes {
+            return Err(format!("Plugin output of {} bytes is larger than its memory", length).into())
+        }
+
+        let mut output = vec![0; length];
+        memory.read(&store, (location >> 32) as usize, &mut output).map_err(|err| err.to_string())?;
+
+        Ok(output)
+    }
+}
+
+// Calls run on the blocking pool so timeout_ms can give up on them, an abandoned call keeps its thread until the fuel runs out
+#[async_trait]
+impl MiddlewareHandler for Plugin {
+    async fn request(&self, container: &mut ContainerHandler, middleware: &Middleware) -> Result<RequestAnswer> {
+        let message = container.into_middleware_request(middleware)?;
+        let (plugin, v2) = (self.clone(), middleware.v2());
+
+        tokio::task::spawn_blocking(move || plugin.handle_request(&message, v2)).await?
+    }
+
+    async fn response(&self, container: &mut ContainerHandler, middleware: &Middleware) -> Result<ResponseAnswer> {
+        let message = container.into_middleware_response(middleware)?;
+        let (plugin, v2) = (self.clone(), middleware.v2());
+
+        tokio::task::spawn_blocking(move || plugin.handle_response(&message, v2)).await?
+    }
+}
+
+fn encode<T: Message>(message: &T) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(message.encoded_len());
+    message.encode(&mut bytes)?;
+
+    Ok(bytes)
+}
@@ -0,0 +1,94 @@
+use hyper::{Client, Request, Body};
+use hyper::client::HttpConnector;
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use crate::kubeware::{RequestRequest, RequestResponse, ResponseRequest, ResponseResponse};
+use crate::middleware::Middleware;
+use crate::container_handler::{ContainerHandler, RequestAnswer, ResponseAnswer};
+use crate::handler::MiddlewareHandler;
+use async_trait::async_trait;
+
+type GenericError = Box<dyn std::error::Error + Send + Sync>;
+type Result<T> = std::result::Result<T, GenericError>;
+
+const JSON_CONTENT_TYPE: &str = "application/json";
+
+// Stages are told apart by path, e.g. http://auth/kubeware/request and http://auth/kubeware/response
+pub async fn request(connection: &Client<HttpConnector>, middleware: &Middleware, message: &RequestRequest) -> Result<RequestResponse> {
+    post(connection, middleware, "request", message).await
+}
+
+pub async fn response(connection: &Client<HttpConnector>, middleware: &Middleware, message: &ResponseRequest) -> Result<ResponseResponse> {
+    post(connection, middleware, "response", message).await
+}
+
+#[async_trait]
+impl MiddlewareHandler for Client<HttpConnector> {
+    async fn request(&self, container: &mut ContainerHandler, middleware: &Middleware) -> Result<RequestAnswer> {
+        Ok(RequestAnswer::V1(request(self, middleware, &container.into_middleware_request(middleware)?).await?))
+    }
+
+    async fn response(&self, container: &mut ContainerHandler, middleware: &Middleware) -> Result<ResponseAnswer> {
+        Ok(ResponseAnswer::V1(response(self, middleware, &container.into_middleware_response(middleware)?).await?))
+    }
+}
+
+async fn post<T: Serialize, R: DeserializeOwned>(connection: &Client<HttpConnector>, middleware: &Middleware, stage: &str, message: &T) -> Result<R> {
+    let url = [middleware.url().trim_end_matches('/'), stage].join("/");
+    let request = Request::post(url)
+        .header(CONTENT_TYPE, HeaderValue::from_static(JSON_CONTENT_TYPE))
+        .body(Body::from(serde_json::to_vec(message)?))?;
+
+    let response = connection.request(request).await?;
+
+    if !response.status().is_success() {
+        return Err(format!("Middleware answered with {}", response.status()).into())
+    }
+
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+
+    Ok(serde_json::from_slice(&body)?)
+}
+
+// Status is written by name ("SUCCESS"), both names and numbers are accepted
+pub mod status {
+    use serde::{Serializer, Deserializer, Deserialize};
+    use serde::de::Error;
+    use crate::kubeware::ResponseStatus;
+
+    #[derive(serde_derive::Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Name(String),
+        Number(i32)
+    }
+
+    fn name(status: ResponseStatus) -> &'static str {
+        match status {
+            ResponseStatus::Success => "SUCCESS",
+            ResponseStatus::Continue => "CONTINUE",
+            ResponseStatus::Stop => "STOP",
+            ResponseStatus::Respond => "RESPOND",
+            ResponseStatus::SkipStage => "SKIP_STAGE"
+        }
+    }
+
+    pub fn serialize<S: Serializer>(value: &i32, serializer: S) -> Result<S::Ok, S::Error> {
+        match ResponseStatus::from_i32(*value) {
+            Some(val) => serializer.serialize_str(name(val)),
+            None => serializer.serialize_i32(*value)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i32, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::Number(val) => Ok(val),
+            Value::Name(val) => (0..=4)
+                .filter_map(ResponseStatus::from_i32)
+                .find(|x| name(*x) == val)
+                .map(|x| x as i32)
+                .ok_or_else(|| D::Error::custom(format!("unknown status {}", val)))
+        }
+    }
+}